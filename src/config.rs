@@ -1,6 +1,14 @@
+use crate::okx::backfill::BackfillConfig;
+use crate::okx::bar_builder;
+use crate::okx::order_id;
+use crate::okx::protocol::{self, ChannelType, Endpoint, TradingEnv};
+use crate::okx::proxy::ProxyConfig;
+use crate::okx::tls::TlsOptions;
+use crate::okx::watchdog::WatchdogConfig;
 use dotenv::dotenv;
-use log::info;
+use log::{info, warn, error};
 use std::env;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
@@ -9,7 +17,30 @@ pub struct AppConfig {
     pub okx_passphrase: String,
     pub simulation_mode: bool,
     
-    pub proxy_url: Option<String>,
+    pub proxy: Option<ProxyConfig>,
+    pub tls: TlsOptions,
+    pub login_timeout: Duration,
+    // 单次 REST 请求 (建连 + 读完响应) 的超时
+    pub rest_timeout: Duration,
+    pub clock_sync_interval: Duration,
+    pub watchdog: WatchdogConfig,
+    // clOrdId 前缀 (区分多个实例 / 账户)
+    pub cl_ord_id_prefix: String,
+    // 本地深度使用的频道 (books / books5 / bbo-tbt / books-l2-tbt)
+    pub book_channel: ChannelType,
+    // 本地 K 线合成周期 (毫秒)
+    pub bar_intervals: Vec<i64>,
+    // business 端点订阅的 OKX K 线周期 (如 1m / 1H)
+    pub candle_bars: Vec<String>,
+    pub backfill: BackfillConfig,
+
+    // 🌐 WebSocket 端点 (支持 wss:// 与明文 ws://)
+    pub ws_public_url: String,
+    pub ws_private_url: String,
+    pub ws_business_url: String,
+
+    // 🌍 REST 地址 (实盘 / 模拟盘共用域名，模拟盘靠 Header 区分)
+    pub rest_url: String,
 }
 
 impl AppConfig {
@@ -27,17 +58,184 @@ impl AppConfig {
             .unwrap_or(true);
 
         // [新增] 读取代理配置 (允许为空，万一以后你在国外跑就不需要了)
-        let proxy = env::var("PROXY_URL").ok();
+        // 地址非法时在启动阶段直接报错退出，而不是等到连接时 panic
+        let proxy = env::var("PROXY_URL").ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| match ProxyConfig::parse(&p) {
+                Ok(cfg) => cfg,
+                Err(e) => {
+                    error!("❌ PROXY_URL 配置无效 ({}): {}", p, e);
+                    std::process::exit(1);
+                }
+            });
         if let Some(ref p) = proxy {
             info!("🌐 [网络] 已启用代理服务: {}", p);
         }
 
+        // [新增] TLS 安全选项: 自定义 CA / SPKI 钉扎 / (危险) 跳过校验
+        let tls_insecure = env::var("TLS_INSECURE_SKIP_VERIFY")
+            .map(|v| v.parse::<bool>().unwrap_or(false))
+            .unwrap_or(false);
+        let tls = match TlsOptions::new(
+            env::var("TLS_CA_BUNDLE").ok().filter(|p| !p.trim().is_empty()).as_deref(),
+            env::var("TLS_PINNED_SPKI").ok().as_deref(),
+            tls_insecure,
+        ) {
+            Ok(t) => t,
+            Err(e) => {
+                error!("❌ TLS 配置无效: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if tls.extra_root_count() > 0 {
+            info!("🔐 [TLS] 已追加信任 {} 张自定义 CA 证书", tls.extra_root_count());
+        }
+        if tls.pin_count() > 0 {
+            info!("📌 [TLS] 已启用 SPKI 钉扎 ({} 个指纹)", tls.pin_count());
+        }
+        if tls.insecure_skip_verify {
+            error!("☠️☠️☠️ [TLS] TLS_INSECURE_SKIP_VERIFY=true —— 证书校验已关闭，API Key 与订单可被中间人窃取! ☠️☠️☠️");
+        }
+
+        // [新增] 登录响应超时 / REST 请求超时 / 服务器校时间隔
+        let login_timeout = env_secs("LOGIN_TIMEOUT_SECS", 10);
+        let rest_timeout = env_secs("REST_TIMEOUT_SECS", 10);
+        let clock_sync_interval = env_secs("CLOCK_SYNC_INTERVAL_SECS", 60);
+
+        // [新增] 静默看门狗
+        let watchdog = WatchdogConfig {
+            ping_interval: env_secs("WS_PING_INTERVAL_SECS", 15),
+            pong_timeout: env_secs("WS_PONG_TIMEOUT_SECS", 5),
+            silence_pause: env_secs("WS_SILENCE_PAUSE_SECS", 5),
+            silence_reconnect: env_secs("WS_SILENCE_RECONNECT_SECS", 20),
+            instrument_stale: env_secs("INSTRUMENT_STALE_SECS", 60),
+        };
+
+        // [新增] clOrdId 前缀: 多实例共用一个账户时必须各不相同
+        let cl_ord_id_prefix = env::var("CL_ORD_ID_PREFIX").ok()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| order_id::DEFAULT_PREFIX.to_string());
+        if let Err(e) = order_id::validate_prefix(&cl_ord_id_prefix) {
+            error!("❌ CL_ORD_ID_PREFIX 配置无效: {}", e);
+            std::process::exit(1);
+        }
+
+        // [新增] 深度频道: books-l2-tbt 需要 VIP 等级，默认 books (400 档)
+        let book_channel_name = env::var("ORDER_BOOK_CHANNEL").ok()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .unwrap_or_else(|| "books".to_string());
+        let book_channel = match ChannelType::book_channel(&book_channel_name) {
+            Some(c) => c,
+            None => {
+                error!("❌ ORDER_BOOK_CHANNEL 配置无效: {} (可选 books / books5 / bbo-tbt / books-l2-tbt)", book_channel_name);
+                std::process::exit(1);
+            }
+        };
+
+        // [新增] K 线: 本地合成周期 + 交易所 candle 频道周期，任一非法都拒绝启动
+        let bar_intervals = env_list("BAR_INTERVALS", "5s,1m").iter()
+            .map(|spec| bar_builder::parse_interval(spec).unwrap_or_else(|| {
+                error!("❌ BAR_INTERVALS 配置无效: {} (示例: 1s,5s,1m,1H)", spec);
+                std::process::exit(1);
+            }))
+            .collect();
+        let candle_bars = env_list("CANDLE_BARS", "1m");
+        if let Some(bar) = candle_bars.iter().find(|b| !protocol::CANDLE_BARS.contains(&b.as_str())) {
+            error!("❌ CANDLE_BARS 配置无效: {} (可选 {})", bar, protocol::CANDLE_BARS.join(" / "));
+            std::process::exit(1);
+        }
+
+        // [新增] 启动 / 重连时的历史回补 (K 线周期与 candle 频道一致)
+        let backfill = BackfillConfig {
+            bars: candle_bars.clone(),
+            candles: env_count("BACKFILL_CANDLES", 300),
+            trades: env_count("BACKFILL_TRADES", 500),
+        };
+
+        // [新增] 端点地址：未配置时使用当前环境的官方默认地址
+        let trading_env = TradingEnv::from_simulation(sim_mode);
+        let ws_url = |key: &str, endpoint: Endpoint| {
+            match env::var(key) {
+                Ok(url) if !url.trim().is_empty() => {
+                    info!("🌐 [网络] {:?} 端点已覆盖: {}", endpoint, url);
+                    url.trim().to_string()
+                }
+                _ => endpoint.default_url(trading_env).to_string(),
+            }
+        };
+        let ws_public_url = ws_url("OKX_WS_PUBLIC_URL", Endpoint::Public);
+        let ws_private_url = ws_url("OKX_WS_PRIVATE_URL", Endpoint::Private);
+        let ws_business_url = ws_url("OKX_WS_BUSINESS_URL", Endpoint::Business);
+
+        // 🚨 高亮当前交易环境，防止实盘 Key 误当模拟盘使用 (显示覆盖后实际连接的私有端点)
+        match trading_env {
+            TradingEnv::Demo => warn!("🧪 [环境] 模拟盘 DEMO ({}) —— 订单不会产生真实成交", ws_private_url),
+            TradingEnv::Live => warn!("🔥🔥🔥 [环境] 实盘 LIVE ({}) —— 所有订单均为真实资金! 🔥🔥🔥", ws_private_url),
+        }
+        let rest_url = env::var("OKX_REST_URL").ok()
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .unwrap_or_else(|| "https://www.okx.com".to_string());
+
         AppConfig {
             okx_api_key: api_key,
             okx_secret_key: secret_key,
             okx_passphrase: passphrase,
             simulation_mode: sim_mode,
-            proxy_url: proxy, // 赋值
+            proxy, // 赋值
+            tls,
+            login_timeout,
+            rest_timeout,
+            clock_sync_interval,
+            watchdog,
+            cl_ord_id_prefix,
+            book_channel,
+            bar_intervals,
+            candle_bars,
+            backfill,
+            ws_public_url,
+            ws_private_url,
+            ws_business_url,
+            rest_url,
         }
     }
-}
\ No newline at end of file
+
+    pub fn endpoint_url(&self, endpoint: Endpoint) -> &str {
+        match endpoint {
+            Endpoint::Public => &self.ws_public_url,
+            Endpoint::Private => &self.ws_private_url,
+            Endpoint::Business => &self.ws_business_url,
+        }
+    }
+
+    pub fn trading_env(&self) -> TradingEnv {
+        TradingEnv::from_simulation(self.simulation_mode)
+    }
+}
+
+/// 读取秒数配置 (缺失 / 非法 / 为 0 时使用默认值)
+fn env_secs(key: &str, default: u64) -> Duration {
+    let secs = env::var(key).ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
+/// 读取逗号分隔的列表 (未配置时使用默认值，显式置空表示不启用)
+fn env_list(key: &str, default: &str) -> Vec<String> {
+    env::var(key).unwrap_or_else(|_| default.to_string())
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// 读取数量配置 (缺失 / 非法时使用默认值，0 表示关闭)
+fn env_count(key: &str, default: usize) -> usize {
+    env::var(key).ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(default)
+}
@@ -1,11 +1,15 @@
 // src/main.rs
 use crate::config::AppConfig;
-use crate::okx::client::{OkxClient, Endpoint};
-use crate::okx::protocol::{self, ChannelType};
+use crate::okx::client::Endpoint;
+use crate::okx::error::OkxError;
+use crate::okx::instruments::InstrumentRegistry;
+use crate::okx::order_id;
+use crate::okx::protocol::{ChannelType, InstType, WsArg};
+use crate::okx::rest::RestClient;
+use crate::okx::supervisor::{ConnectionSupervisor, Link};
 use crate::strategy::market::MarketStrategy;
-use futures_util::{SinkExt, StreamExt};
-use log::{info, error};
-use tokio_tungstenite::tungstenite::Message;
+use log::{info, warn, error};
+use std::sync::Arc;
 
 mod config;
 mod okx;
@@ -19,28 +23,83 @@ async fn main() {
     info!("🏴‍☠️  Rust HFT Sniper Bot v1.0 [Profit First]");
     let config = AppConfig::load();
 
-    // 1. 行情连接
-    let client_pub = OkxClient::new(Endpoint::Public);
-    let ws_pub = client_pub.connect(&config).await.unwrap(); // 偷懒unwrap，如果挂了直接panic重启
-    let (mut write_pub, read_pub) = ws_pub.split();
+    if let Err(e) = order_id::init(&config.cl_ord_id_prefix) {
+        error!("⛔ {}", e);
+        std::process::exit(1);
+    }
+    info!("🆔 [订单] clOrdId 前缀: {}", config.cl_ord_id_prefix);
+
+    // 0. 服务器校时 (登录签名依赖准确时间，必须先于私有连接)
+    let rest = RestClient::new(&config);
+    match rest.sync_clock().await {
+        Ok(sample) => info!("⏱️ [校时] 本机与 OKX 偏移 {}ms (RTT {}ms)", sample.offset_ms, sample.rtt_ms),
+        Err(e) => warn!("⚠️ [校时] 首次校时失败，暂用本机时间: {}", e),
+    }
+
+    // 产品注册表 (tickSz / lotSz / minSz / 状态)，之后由 instruments 频道保持最新
+    // 全部请求共用一个截止时间：交易所无响应时明确报错退出，而不是卡在启动阶段
+    let load = tokio::time::timeout(config.rest_timeout, InstrumentRegistry::load(&rest, &[InstType::Spot])).await
+        .unwrap_or_else(|_| Err(OkxError::Timeout(format!("加载产品信息超过 {}s", config.rest_timeout.as_secs()))));
+    let instruments = match load {
+        Ok(registry) => Arc::new(registry),
+        Err(e @ OkxError::Timeout(_)) => {
+            error!("⛔ 无法加载产品信息 (请检查网络 / 代理，或调大 REST_TIMEOUT_SECS)，程序退出: {}", e);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("⛔ 无法加载产品信息，程序退出: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // 历史回补用独立的 REST 客户端 (校时任务会接管 rest)
+    let backfill_rest = RestClient::new(&config);
+    tokio::spawn(rest.run_clock_sync(config.clock_sync_interval));
+
+    // 1. 行情连接 (由守护者负责断线重连 + 订阅重放)
+    let sup_pub = ConnectionSupervisor::new(Endpoint::Public);
 
-    // 订阅列表 (10个精选)
+    // 订阅列表 (10个精选)，合并为一个批量订阅请求
     let watchlist = vec!["WIF-USDT", "PEPE-USDT", "BONK-USDT", "DOGE-USDT", "SOL-USDT", "JUP-USDT", "WLD-USDT", "ORDI-USDT", "SUI-USDT", "NEAR-USDT"];
+    let mut public_args: Vec<WsArg> = watchlist.iter().map(|inst_id| WsArg::for_inst(ChannelType::Tickers, inst_id)).collect();
+    public_args.extend(watchlist.iter().map(|inst_id| WsArg::for_inst(config.book_channel, inst_id)));
+    public_args.extend(watchlist.iter().map(|inst_id| WsArg::for_inst(ChannelType::Trades, inst_id)));
+    public_args.push(WsArg::for_inst_type(ChannelType::Instruments, InstType::Spot.as_str()));
+    sup_pub.subscribe(public_args);
 
-    for inst_id in watchlist {
-        let sub = protocol::create_subscribe_packet(ChannelType::Tickers, inst_id);
-        write_pub.send(Message::Text(sub)).await.unwrap();
-    }
+    // 2. 交易连接 (重连时自动重新 login)，账户频道订阅全部币种，订单频道订阅全部产品类型
+    let sup_priv = ConnectionSupervisor::new(Endpoint::Private);
+    sup_priv.subscribe(vec![
+        WsArg::for_ccy(ChannelType::Account, None),
+        WsArg::for_inst_type(ChannelType::Orders, "ANY"),
+    ]);
+
+    // 3. 业务连接 (K 线频道只在 business 端点提供)
+    let sup_biz = ConnectionSupervisor::new(Endpoint::Business);
+    sup_biz.subscribe(config.candle_bars.iter()
+        .flat_map(|bar| watchlist.iter().map(move |inst_id| WsArg::candle(bar, inst_id)))
+        .collect());
 
-    // 2. 交易连接
-    let client_priv = OkxClient::new(Endpoint::Private);
-    let ws_priv = client_priv.connect(&config).await.unwrap();
-    let (mut write_priv, read_priv) = ws_priv.split();
+    // 4. 先用历史数据预热 (此时还没有连接，不会有连接因无人读写而超时)，再建立连接
+    let strategy = MarketStrategy::new(config.watchdog.clone(), instruments, &config.bar_intervals);
+    strategy.warm_up(&backfill_rest, &watchlist, &config.backfill).await;
 
-    let sub_acc = protocol::create_subscribe_packet(ChannelType::Account, "USDT");
-    write_priv.send(Message::Text(sub_acc)).await.unwrap();
+    let mut public = open_or_exit(sup_pub, &config).await;
+    let mut private = open_or_exit(sup_priv, &config).await;
+    let mut business = open_or_exit(sup_biz, &config).await;
 
-    // 3. 启动
-    let strategy = MarketStrategy::new();
-    strategy.run(read_pub, write_pub, read_priv, write_priv).await;
+    // 5. 启动 (断线 -> 通知策略 -> 只在后台重连断掉的那一路，其余连接照常服务)
+    let fatal = strategy.run(&mut public, &mut private, &mut business, &backfill_rest, &watchlist, &config.backfill).await;
+    error!("⛔ 无法重建连接，程序退出: {}", fatal);
+    std::process::exit(1);
+}
+
+/// 守护者只会把致命错误 (配置错误 / 鉴权被拒) 抛上来，此时重试无意义，直接停机
+async fn open_or_exit(sup: ConnectionSupervisor, config: &AppConfig) -> Link {
+    match Link::open(sup, config).await {
+        Ok(link) => link,
+        Err(e) => {
+            error!("⛔ 无法建立连接，程序退出: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
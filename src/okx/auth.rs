@@ -1,13 +1,14 @@
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use base64::{Engine as _, engine::general_purpose};
+use crate::okx::error::{OkxError, OkxResult};
 
 // 定义 HMAC-SHA256 类型别名
 type HmacSha256 = Hmac<Sha256>;
 
 /// 生成 OKX WebSocket 鉴权签名
 /// 公式: Base64(HmacSHA256(timestamp + "GET" + "/users/self/verify", secret_key))
-pub fn generate_sign(secret: &str, timestamp: &str) -> String {
+pub fn generate_sign(secret: &str, timestamp: &str) -> OkxResult<String> {
     let method = "GET";
     let request_path = "/users/self/verify";
     let body = ""; // 登录消息体为空
@@ -19,14 +20,12 @@ pub fn generate_sign(secret: &str, timestamp: &str) -> String {
 
     // 2. 初始化 HMAC 计算器
     let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-        .expect("HMAC 初始化失败: Key 长度无效");
+        .map_err(|_| OkxError::Config("HMAC 初始化失败: Key 长度无效".to_string()))?;
 
     // 3. 注入数据
     mac.update(message.as_bytes());
 
     // 4. 计算并 Base64 编码
     let result = mac.finalize();
-    let sign = general_purpose::STANDARD.encode(result.into_bytes());
-
-    sign
+    Ok(general_purpose::STANDARD.encode(result.into_bytes()))
 }
\ No newline at end of file
@@ -0,0 +1,168 @@
+// src/okx/backfill.rs
+
+use crate::okx::error::{OkxError, OkxResult};
+use crate::okx::market_data::{Candle, Trade};
+use crate::okx::rest::RestClient;
+use log::warn;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// OKX 行情接口限频 (按 IP): candles 40 次/2s，history-candles 20 次/2s，trades 100 次/2s，history-trades 20 次/2s
+// 统一按最严格的 20 次/2s 节流，并留一点余量给其他 REST 调用
+const REQUEST_GAP: Duration = Duration::from_millis(120);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// 触发限频后的退避与重试次数
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_RETRIES: u32 = 3;
+
+const CANDLES_PAGE: usize = 300; // /market/candles 单页上限 (只覆盖最近 1440 根)
+const HISTORY_CANDLES_PAGE: usize = 100; // /market/history-candles 单页上限
+const TRADES_PAGE: usize = 500; // /market/trades 只返回最近 500 笔
+const HISTORY_TRADES_PAGE: usize = 100; // /market/history-trades 单页上限
+
+/// ⚙️ 启动 / 重连时的历史回补参数 (来自 AppConfig)，数量为 0 表示不回补
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    /// 回补的 K 线周期 (与 candle 频道一致)
+    pub bars: Vec<String>,
+    /// 每个品种 / 周期回补的已收盘 K 线根数
+    pub candles: usize,
+    /// 每个品种回补的最近成交笔数
+    pub trades: usize,
+}
+
+/// 📦 一次回补拉到的全部数据 (旧 -> 新)
+#[derive(Debug, Default)]
+pub struct WarmUpData {
+    /// (品种, 成交)
+    pub trades: Vec<(String, Vec<Trade>)>,
+    /// (品种, 周期, 已收盘 K 线)
+    pub candles: Vec<(String, String, Vec<Candle>)>,
+}
+
+/// 🔥 按品种依次回补成交与 K 线，总耗时不超过 budget (超出时返回已拉到的部分)
+/// 单个品种失败只告警，不影响其他品种
+pub async fn fetch(rest: &RestClient, watchlist: &[&str], cfg: &BackfillConfig, budget: Duration) -> WarmUpData {
+    let mut backfill = Backfill::new(rest, Instant::now() + budget);
+    let mut data = WarmUpData::default();
+    for inst_id in watchlist {
+        if backfill.expired() {
+            warn!("⏰ [回补] 超过 {}s 预算，剩余品种跳过", budget.as_secs());
+            break;
+        }
+        if cfg.trades > 0 {
+            match backfill.trades(inst_id, cfg.trades).await {
+                Ok(trades) => data.trades.push((inst_id.to_string(), trades)),
+                Err(e) => warn!("⚠️ [回补] {} 成交回补失败: {}", inst_id, e),
+            }
+        }
+        if cfg.candles == 0 { continue; }
+        for bar in &cfg.bars {
+            match backfill.candles(inst_id, bar, cfg.candles).await {
+                Ok(candles) => data.candles.push((inst_id.to_string(), bar.clone(), candles)),
+                Err(e) => warn!("⚠️ [回补] {} {} K 线回补失败: {}", inst_id, bar, e),
+            }
+        }
+    }
+    data
+}
+
+/// 📥 [历史回补] 分页拉取 K 线 / 成交，所有请求串行并按限频节流
+/// 所有等待 (节流 / 单次请求 / 限频退避) 都不会越过截止时间
+pub struct Backfill<'a> {
+    rest: &'a RestClient,
+    last_request: Option<Instant>,
+    deadline: Instant,
+}
+
+impl<'a> Backfill<'a> {
+    pub fn new(rest: &'a RestClient, deadline: Instant) -> Self {
+        Backfill { rest, last_request: None, deadline }
+    }
+
+    fn expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// 节流 + 超时 + 限频退避重试
+    async fn get<T: DeserializeOwned>(&mut self, path: &str) -> OkxResult<Vec<T>> {
+        let mut attempt = 0;
+        loop {
+            if let Some(last) = self.last_request {
+                tokio::time::sleep_until((last + REQUEST_GAP).min(self.deadline)).await;
+            }
+            if self.expired() {
+                return Err(OkxError::Timeout(format!("GET {} 超过回补截止时间", path)));
+            }
+            self.last_request = Some(Instant::now());
+
+            let deadline = (Instant::now() + REQUEST_TIMEOUT).min(self.deadline);
+            let result = tokio::time::timeout_at(deadline, self.rest.get(path)).await
+                .unwrap_or_else(|_| Err(OkxError::Timeout(format!("GET {} 超时", path))));
+            match result {
+                Err(OkxError::RateLimit { code, msg }) if attempt < MAX_RETRIES && Instant::now() + RATE_LIMIT_BACKOFF < self.deadline => {
+                    attempt += 1;
+                    warn!("🚦 [回补] 触发限频 [{}] {}，{}s 后第 {} 次重试", code, msg, RATE_LIMIT_BACKOFF.as_secs(), attempt);
+                    tokio::time::sleep(RATE_LIMIT_BACKOFF).await;
+                }
+                other => return other,
+            }
+        }
+    }
+
+    /// 🕯️ 最近 count 根已收盘 K 线 (旧 -> 新)
+    /// 先翻 /market/candles，翻到头后改用 /market/history-candles 继续往前翻
+    pub async fn candles(&mut self, inst_id: &str, bar: &str, count: usize) -> OkxResult<Vec<Candle>> {
+        let mut out: Vec<Candle> = Vec::new(); // 新 -> 旧
+        let mut after: Option<i64> = None;
+        let mut history = false;
+        while out.len() < count {
+            let (endpoint, page) = if history { ("history-candles", HISTORY_CANDLES_PAGE) } else { ("candles", CANDLES_PAGE) };
+            // 多要一根: 第一页通常包含尚未收盘的当前 K 线
+            let limit = page.min(count - out.len() + 1);
+            let mut path = format!("/api/v5/market/{}?instId={}&bar={}&limit={}", endpoint, inst_id, bar, limit);
+            if let Some(ts) = after {
+                path.push_str(&format!("&after={}", ts));
+            }
+
+            let rows: Vec<Vec<String>> = self.get(&path).await?;
+            let oldest = rows.iter().filter_map(|row| Candle::from_row(row)).map(|c| c.ts).min();
+            out.extend(rows.iter().filter_map(|row| Candle::from_row(row)).filter(|c| c.confirmed));
+
+            match oldest {
+                // 没有更早的数据 (或整页无法解析): 最近接口翻到头就换历史接口，历史接口也翻到头则结束
+                None if history => break,
+                None => history = true,
+                Some(ts) => {
+                    after = Some(ts);
+                    if rows.len() < limit {
+                        if history { break; }
+                        history = true;
+                    }
+                }
+            }
+        }
+        out.truncate(count);
+        out.reverse();
+        Ok(out)
+    }
+
+    /// 💱 最近 count 笔成交 (旧 -> 新)
+    /// /market/trades 只有最近 500 笔，更早的用 /market/history-trades 按 tradeId 往前翻
+    pub async fn trades(&mut self, inst_id: &str, count: usize) -> OkxResult<Vec<Trade>> {
+        let mut out: Vec<Trade> = self.get(&format!("/api/v5/market/trades?instId={}&limit={}", inst_id, TRADES_PAGE.min(count))).await?;
+        while out.len() < count {
+            let Some(oldest) = out.last() else { break };
+            let limit = HISTORY_TRADES_PAGE.min(count - out.len());
+            let page: Vec<Trade> = self.get(&format!(
+                "/api/v5/market/history-trades?instId={}&type=1&after={}&limit={}", inst_id, oldest.trade_id, limit
+            )).await?;
+            if page.is_empty() { break; }
+            out.extend(page);
+        }
+        out.truncate(count);
+        out.reverse();
+        Ok(out)
+    }
+}
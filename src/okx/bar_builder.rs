@@ -0,0 +1,282 @@
+// src/okx/bar_builder.rs
+
+use crate::okx::market_data::{Candle, Trade};
+use log::{debug, warn};
+use std::collections::HashMap;
+
+// 长时间断流后最多补齐的空 K 线根数，超出部分直接跳过
+const MAX_FILL_BARS: i64 = 600;
+
+/// "5s" / "1m" / "1H" / "1D" / "1W" -> 毫秒 (大写 M 是月，长度不固定，不支持)
+/// OKX 的 UTC 周期 (如 "1Dutc") 只是对齐时区不同，长度相同
+pub fn parse_interval(spec: &str) -> Option<i64> {
+    let spec = spec.trim().trim_end_matches("utc");
+    let unit_ms = match spec.chars().last()? {
+        's' => 1_000,
+        'm' => 60_000,
+        'H' | 'h' => 3_600_000,
+        'D' | 'd' => 86_400_000,
+        'W' | 'w' => 604_800_000,
+        _ => return None,
+    };
+    let n = spec[..spec.len() - 1].parse::<i64>().ok().filter(|n| *n > 0)?;
+    Some(n * unit_ms)
+}
+
+/// 毫秒 -> 最简周期名 (日志用)
+pub fn format_interval(ms: i64) -> String {
+    match ms {
+        ms if ms % 86_400_000 == 0 => format!("{}D", ms / 86_400_000),
+        ms if ms % 3_600_000 == 0 => format!("{}H", ms / 3_600_000),
+        ms if ms % 60_000 == 0 => format!("{}m", ms / 60_000),
+        ms if ms % 1_000 == 0 => format!("{}s", ms / 1_000),
+        ms => format!("{}ms", ms),
+    }
+}
+
+/// K 线来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BarSource {
+    /// 本地由成交合成
+    Local,
+    /// OKX candle 频道或 REST 历史 K 线
+    Exchange,
+}
+
+/// 📣 已收盘的 K 线事件
+#[derive(Debug, Clone)]
+pub struct ClosedBar {
+    pub inst_id: String,
+    pub interval_ms: i64,
+    pub source: BarSource,
+    pub candle: Candle,
+    /// 该周期内没有任何成交，按上一根收盘价补齐的空 K 线
+    pub filled: bool,
+}
+
+/// 单个品种的合成进度
+struct Slot {
+    current: Option<Candle>,
+    // 下一根待输出 K 线的开盘时间 (早于它的数据都算迟到)
+    next_start: i64,
+    last_close: f64,
+}
+
+/// 🕯️ [K 线合成] 把成交聚合成固定周期的 OHLCV
+/// - 数据跨入新周期时输出上一根；中间没有数据的周期按上一根收盘价补空 K 线
+/// - 长时间无数据由 flush 按时间驱动收盘 (宽限期内仍接受迟到数据)
+/// - 已输出周期的迟到数据直接丢弃并计数，不会改写已发出的 K 线
+pub struct BarBuilder {
+    interval_ms: i64,
+    grace_ms: i64,
+    slots: HashMap<String, Slot>,
+    late: u64,
+}
+
+impl BarBuilder {
+    pub fn new(interval_ms: i64, grace_ms: i64) -> Self {
+        BarBuilder { interval_ms, grace_ms, slots: HashMap::new(), late: 0 }
+    }
+
+    pub fn interval_ms(&self) -> i64 {
+        self.interval_ms
+    }
+
+    /// 累计丢弃的迟到数据条数
+    pub fn late_count(&self) -> u64 {
+        self.late
+    }
+
+    /// 成交: 价格 + 成交量
+    pub fn on_trade(&mut self, trade: &Trade) -> Vec<ClosedBar> {
+        let Ok(ts) = trade.ts.parse::<i64>() else { return Vec::new() };
+        self.on_price(&trade.inst_id, ts, trade.px, trade.sz)
+    }
+
+    pub fn on_price(&mut self, inst_id: &str, ts: i64, px: f64, sz: f64) -> Vec<ClosedBar> {
+        let start = ts - ts.rem_euclid(self.interval_ms);
+        let slot = self.slots.entry(inst_id.to_string()).or_insert(Slot { current: None, next_start: start, last_close: px });
+        if start < slot.next_start {
+            self.late += 1;
+            debug!("🐢 [K线] {} {} 迟到数据已丢弃 (ts {} < {})", inst_id, format_interval(self.interval_ms), ts, slot.next_start);
+            return Vec::new();
+        }
+
+        let closed = Self::close_until(inst_id, self.interval_ms, slot, start);
+        match slot.current.as_mut() {
+            Some(bar) => {
+                bar.high = bar.high.max(px);
+                bar.low = bar.low.min(px);
+                bar.close = px;
+                bar.vol += sz;
+                bar.vol_quote += sz * px;
+            }
+            None => {
+                slot.current = Some(Candle {
+                    ts: start, open: px, high: px, low: px, close: px, vol: sz, vol_quote: sz * px, confirmed: false,
+                });
+            }
+        }
+        closed
+    }
+
+    /// ⏰ 按时间收盘: 结束时间 + 宽限期已过的周期全部输出 (包括没有任何数据的周期)
+    pub fn flush(&mut self, now: i64) -> Vec<ClosedBar> {
+        let cutoff = now - self.grace_ms;
+        let boundary = cutoff - cutoff.rem_euclid(self.interval_ms);
+        let mut closed = Vec::new();
+        for (inst_id, slot) in self.slots.iter_mut() {
+            closed.extend(Self::close_until(inst_id, self.interval_ms, slot, boundary));
+        }
+        closed
+    }
+
+    /// 断线后数据有缺口，丢弃全部进度 (不补空 K 线，避免伪造行情)
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// 输出开盘时间早于 boundary 的所有 K 线
+    fn close_until(inst_id: &str, interval_ms: i64, slot: &mut Slot, boundary: i64) -> Vec<ClosedBar> {
+        let mut closed = Vec::new();
+        while slot.next_start < boundary {
+            if slot.current.is_none() && boundary - slot.next_start > MAX_FILL_BARS * interval_ms {
+                let skipped = (boundary - slot.next_start) / interval_ms;
+                warn!("🕳️ [K线] {} {} 连续 {} 根无数据，跳过补齐", inst_id, format_interval(interval_ms), skipped);
+                slot.next_start = boundary;
+                break;
+            }
+            let (candle, filled) = match slot.current.take_if(|c| c.ts == slot.next_start) {
+                Some(bar) => (bar, false),
+                None => {
+                    let px = slot.last_close;
+                    (Candle { ts: slot.next_start, open: px, high: px, low: px, close: px, vol: 0.0, vol_quote: 0.0, confirmed: false }, true)
+                }
+            };
+            slot.last_close = candle.close;
+            slot.next_start += interval_ms;
+            closed.push(ClosedBar {
+                inst_id: inst_id.to_string(),
+                interval_ms,
+                source: BarSource::Local,
+                candle: Candle { confirmed: true, ..candle },
+                filled,
+            });
+        }
+        closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: i64 = 1_000;
+
+    fn ohlcv(bar: &ClosedBar) -> (i64, f64, f64, f64, f64, f64) {
+        let c = &bar.candle;
+        (c.ts, c.open, c.high, c.low, c.close, c.vol)
+    }
+
+    #[test]
+    fn interval_round_trip() {
+        assert_eq!(parse_interval("5s"), Some(5 * SEC));
+        assert_eq!(parse_interval("1m"), Some(60 * SEC));
+        assert_eq!(parse_interval("4H"), Some(4 * 3_600 * SEC));
+        assert_eq!(parse_interval("1Dutc"), Some(86_400 * SEC));
+        assert_eq!(parse_interval("1M"), None);
+        assert_eq!(parse_interval("0m"), None);
+        assert_eq!(format_interval(4 * 3_600 * SEC), "4H");
+        assert_eq!(format_interval(90 * SEC), "90s");
+    }
+
+    #[test]
+    fn bar_closes_exactly_at_the_boundary() {
+        let mut b = BarBuilder::new(SEC, 0);
+        assert!(b.on_price("BTC-USDT", 0, 10.0, 1.0).is_empty());
+        assert!(b.on_price("BTC-USDT", 400, 12.0, 1.0).is_empty());
+        // 周期最后一毫秒仍属于当前 K 线
+        assert!(b.on_price("BTC-USDT", SEC - 1, 9.0, 2.0).is_empty());
+
+        // 下一周期的第一笔成交触发收盘
+        let closed = b.on_price("BTC-USDT", SEC, 11.0, 1.0);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(ohlcv(&closed[0]), (0, 10.0, 12.0, 9.0, 9.0, 4.0));
+        assert!(closed[0].candle.confirmed);
+        assert!(!closed[0].filled);
+        assert_eq!(closed[0].source, BarSource::Local);
+
+        // 按时间收盘: 差 1ms 不收，到点就收
+        assert!(b.flush(2 * SEC - 1).is_empty());
+        let closed = b.flush(2 * SEC);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(ohlcv(&closed[0]), (SEC, 11.0, 11.0, 11.0, 11.0, 1.0));
+    }
+
+    #[test]
+    fn flush_waits_for_the_grace_period() {
+        let mut b = BarBuilder::new(SEC, 200);
+        b.on_price("BTC-USDT", 100, 10.0, 1.0);
+        assert!(b.flush(SEC + 199).is_empty());
+
+        // 宽限期内的迟到数据仍计入
+        assert!(b.on_price("BTC-USDT", 900, 8.0, 1.0).is_empty());
+        let closed = b.flush(SEC + 200);
+        assert_eq!(ohlcv(&closed[0]), (0, 10.0, 10.0, 8.0, 8.0, 2.0));
+
+        // 已输出周期的数据被丢弃
+        assert!(b.on_price("BTC-USDT", 950, 1.0, 1.0).is_empty());
+        assert_eq!(b.late_count(), 1);
+    }
+
+    #[test]
+    fn gaps_are_filled_with_the_last_close() {
+        let mut b = BarBuilder::new(SEC, 0);
+        b.on_price("BTC-USDT", 0, 10.0, 1.0);
+        b.on_price("BTC-USDT", 500, 11.0, 1.0);
+
+        let closed = b.on_price("BTC-USDT", 3 * SEC + 500, 20.0, 1.0);
+        assert_eq!(closed.iter().map(|c| c.filled).collect::<Vec<_>>(), [false, true, true]);
+        assert_eq!(ohlcv(&closed[1]), (SEC, 11.0, 11.0, 11.0, 11.0, 0.0));
+        assert_eq!(ohlcv(&closed[2]), (2 * SEC, 11.0, 11.0, 11.0, 11.0, 0.0));
+
+        // flush 同样补空 K 线
+        let closed = b.flush(6 * SEC);
+        assert_eq!(closed.iter().map(|c| (c.candle.ts, c.filled)).collect::<Vec<_>>(), [(3 * SEC, false), (4 * SEC, true), (5 * SEC, true)]);
+        assert!(closed.iter().all(|c| c.candle.close == 20.0));
+    }
+
+    #[test]
+    fn gap_fill_is_capped() {
+        let mut b = BarBuilder::new(SEC, 0);
+        b.on_price("BTC-USDT", 0, 10.0, 1.0);
+        // 缺口恰好 MAX_FILL_BARS 根: 全部补齐
+        let closed = b.on_price("BTC-USDT", (MAX_FILL_BARS + 1) * SEC, 11.0, 1.0);
+        assert_eq!(closed.len() as i64, 1 + MAX_FILL_BARS);
+        assert!(closed[1..].iter().all(|c| c.filled));
+
+        let mut b = BarBuilder::new(SEC, 0);
+        b.on_price("BTC-USDT", 0, 10.0, 1.0);
+        // 多一根: 只输出有数据的那根，空 K 线全部跳过
+        let closed = b.on_price("BTC-USDT", (MAX_FILL_BARS + 2) * SEC, 11.0, 1.0);
+        assert_eq!(closed.len(), 1);
+        assert!(!closed[0].filled);
+
+        // 跳过后从新位置继续
+        let closed = b.flush((MAX_FILL_BARS + 3) * SEC);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(ohlcv(&closed[0]), ((MAX_FILL_BARS + 2) * SEC, 11.0, 11.0, 11.0, 11.0, 1.0));
+    }
+
+    #[test]
+    fn instruments_are_independent() {
+        let mut b = BarBuilder::new(SEC, 0);
+        b.on_price("BTC-USDT", 0, 10.0, 1.0);
+        b.on_price("ETH-USDT", 2 * SEC, 5.0, 1.0);
+        // ETH 的数据不会让 BTC 收盘
+        assert!(b.on_price("ETH-USDT", 3 * SEC, 6.0, 1.0).iter().all(|c| c.inst_id == "ETH-USDT"));
+
+        b.clear();
+        assert!(b.flush(10 * SEC).is_empty());
+    }
+}
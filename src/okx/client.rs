@@ -1,6 +1,10 @@
 // src/okx/client.rs
 
 pub(crate) use crate::okx::{auth, protocol::Endpoint};
+use crate::okx::error::{AuthFailure, OkxError, OkxResult};
+use crate::okx::protocol::WsRouter;
+use crate::utils::time;
+use futures_util::stream::SplitStream;
 use futures_util::{SinkExt, StreamExt};
 use serde_json::json;
 use tokio::net::TcpStream;
@@ -8,96 +12,55 @@ use tokio_tungstenite::{client_async, WebSocketStream};
 use url::Url;
 use log::{info, error, warn};
 
-use async_http_proxy::http_connect_tokio;
-use native_tls::TlsConnector;
 use tokio_native_tls::TlsConnector as TokioTlsConnector;
 use tokio_tungstenite::tungstenite::Message;
-type WsStream = WebSocketStream<tokio_native_tls::TlsStream<TcpStream>>;
+use tokio_tungstenite::MaybeTlsStream;
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub type WsWriteStream = futures_util::stream::SplitSink<WsStream, Message>;
+pub type WsReadStream = SplitStream<WsStream>;
 
 pub struct OkxClient {
     endpoint: Endpoint,
+    // 最近一次登录返回的 connId
+    conn_id: Option<String>,
 }
 
 impl OkxClient {
     pub fn new(endpoint: Endpoint) -> Self {
-        OkxClient { endpoint }
+        OkxClient { endpoint, conn_id: None }
     }
 
-    pub async fn connect(&self, config: &crate::config::AppConfig) -> Option<WsStream> {
-        let url_str = self.endpoint.as_url();
-        let target_url = Url::parse(url_str).unwrap();
-        let target_host = target_url.host_str().unwrap();
-        let target_port = target_url.port_or_known_default().unwrap_or(443);
-
-        // ==========================================
-        // 🚦 智能分支：根据是否配置代理决定连接方式
-        // ==========================================
-        if let Some(proxy_url_str) = &config.proxy_url {
-            // ➤ 分支 A: 走代理 (本地开发)
-            let proxy_url = Url::parse(proxy_url_str).unwrap();
-            let proxy_host = proxy_url.host_str().unwrap();
-            let proxy_port = proxy_url.port().unwrap();
-
-            info!("🔗 [模式] 代理连接: {} -> OKX", proxy_url_str);
-
-            let mut tcp_stream = match TcpStream::connect(format!("{}:{}", proxy_host, proxy_port)).await {
-                Ok(s) => s,
-                Err(e) => { error!("❌ 代理连接失败: {}", e); return None; }
-            };
-            let _ = tcp_stream.set_nodelay(true);
-
-            if let Err(e) = http_connect_tokio(&mut tcp_stream, target_host, target_port).await {
-                error!("❌ 代理握手失败: {}", e); return None;
-            }
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
 
-            // 代理模式下，通常需要跳过证书验证 (防止MITM)
-            let cx = TlsConnector::builder().danger_accept_invalid_certs(true).build().unwrap();
-            let cx = TokioTlsConnector::from(cx);
-            let tls_stream = match cx.connect(target_host, tcp_stream).await {
-                Ok(s) => s, Err(e) => { error!("❌ TLS 失败: {}", e); return None; }
-            };
+    pub fn conn_id(&self) -> Option<&str> {
+        self.conn_id.as_deref()
+    }
 
-            let (ws_stream, _) = client_async(url_str, tls_stream).await.ok()?;
+    /// login: 是否需要鉴权 (由 Endpoint::requires_login 结合订阅内容决定)
+    pub async fn connect(&mut self, config: &crate::config::AppConfig, login: bool) -> OkxResult<WsStream> {
+        let url_str = config.endpoint_url(self.endpoint);
+        let target_url = Url::parse(url_str)
+            .map_err(|e| OkxError::Config(format!("无效的端点地址 {}: {}", url_str, e)))?;
 
-            // 返回流
-            match self.endpoint {
-                Endpoint::Public => Some(ws_stream),
-                Endpoint::Private => self.login(ws_stream, config).await,
-            }
+        let stream = connect_transport(config, &target_url).await?;
+        let (ws_stream, _) = client_async(url_str, stream).await.map_err(|e| OkxError::Handshake(Box::new(e)))?;
 
+        // 返回流
+        if login {
+            self.login(ws_stream, config).await
         } else {
-            // ➤ 分支 B: 直连 (香港/东京服务器)
-            info!("🔗 [模式] 直连 OKX (无代理) -> {}:{}", target_host, target_port);
-
-            let tcp_stream = match TcpStream::connect(format!("{}:{}", target_host, target_port)).await {
-                Ok(s) => s,
-                Err(e) => { error!("❌ 直连失败 (请检查服务器网络): {}", e); return None; }
-            };
-            let _ = tcp_stream.set_nodelay(true);
-
-            // 直连模式下，证书必须是合法的，不能跳过验证！
-            let cx = TlsConnector::builder().build().unwrap();
-            let cx = TokioTlsConnector::from(cx);
-            let tls_stream = match cx.connect(target_host, tcp_stream).await {
-                Ok(s) => s, Err(e) => { error!("❌ TLS 失败: {}", e); return None; }
-            };
-
-            let (ws_stream, _) = client_async(url_str, tls_stream).await.ok()?;
-
-            // 返回流
-            match self.endpoint {
-                Endpoint::Public => Some(ws_stream),
-                Endpoint::Private => self.login(ws_stream, config).await,
-            }
+            Ok(ws_stream)
         }
     }
 
-
     // ✨ [核心修改] 阻塞式登录：发包后等待响应，确认成功才返回
-    async fn login(&self, ws_stream: WsStream, config: &crate::config::AppConfig) -> Option<WsStream> {
+    async fn login(&mut self, ws_stream: WsStream, config: &crate::config::AppConfig) -> OkxResult<WsStream> {
         let (mut write, mut read) = ws_stream.split(); // 注意这里 read 也是 mut
-        let timestamp = chrono::Utc::now().timestamp().to_string();
-        let sign = auth::generate_sign(&config.okx_secret_key, &timestamp);
+        // 使用校准后的服务器时间签名，避免本机时钟漂移导致 60004/60006
+        let timestamp = time::get_timestamp_sec();
+        let sign = auth::generate_sign(&config.okx_secret_key, &timestamp)?;
 
         let login_msg = json!({
             "op": "login",
@@ -110,39 +73,91 @@ impl OkxClient {
         });
 
         // 1. 发送登录请求
-        if let Err(e) = write.send(Message::Text(login_msg.to_string())).await {
-            error!("❌ 登录包发送失败: {}", e);
-            return None;
-        }
+        write.send(Message::Text(login_msg.to_string())).await?;
         info!("📤 登录请求已发送，等待服务器确认...");
 
-        // 2. ⏳ 原地等待响应 (关键！)
-        // 我们只读第一条消息，它必须是登录结果
+        // 2. ⏳ 原地等待响应 (带超时，避免服务器不回包时永久卡死)
+        let router = tokio::time::timeout(config.login_timeout, Self::read_login_response(&mut read))
+            .await
+            .map_err(|_| OkxError::Timeout(format!("{}s 内未收到登录响应", config.login_timeout.as_secs())))??;
+
+        self.conn_id = router.conn_id.clone();
+        let conn_id = router.conn_id.as_deref().unwrap_or("-");
+
+        match (router.event.as_deref(), router.code.as_deref()) {
+            (Some("login"), Some("0")) => {
+                info!("✅ 登录鉴权成功 (Login Authorized) | connId: {}", conn_id);
+                // 3. 登录成功，把流合并回去，交还给 main
+                Ok(write.reunite(read).expect("split 自同一条流"))
+            }
+            (_, code) => {
+                let err = OkxError::from_api(code.unwrap_or_default(), router.msg.as_deref().unwrap_or_default(), true);
+                error!("❌ 登录被拒绝: {} | connId: {}", err, conn_id);
+                if let OkxError::Auth { kind: AuthFailure::EnvironmentMismatch, .. } = err {
+                    // 模式与 Key 环境不一致继续重试毫无意义，直接拒绝启动
+                    error!(
+                        "❌ API Key 与当前环境不匹配 (SIMULATION_MODE={} -> {})，请检查 .env",
+                        config.simulation_mode, config.trading_env().label()
+                    );
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// 读取第一条 login / error 事件 (忽略 Ping/Pong 等其他帧)
+    async fn read_login_response(read: &mut WsReadStream) -> OkxResult<WsRouter> {
         while let Some(msg_res) = read.next().await {
-            match msg_res {
-                Ok(Message::Text(text)) => {
-                    // 解析 JSON 检查 code
-                    // 简易解析，只要包含 "login" 和 "0" 就认为成功
-                    if text.contains("\"event\":\"login\"") && text.contains("\"code\":\"0\"") {
-                        info!("✅ 登录鉴权成功 (Login Authorized)");
-                        // 3. 登录成功，把流合并回去，交还给 main
-                        return Some(write.reunite(read).unwrap());
-                    } else if text.contains("\"event\":\"error\"") {
-                        error!("❌ 登录被拒绝: {}", text);
-                        return None;
-                    } else {
-                        warn!("⚠️ 收到非登录响应 (忽略): {}", text);
-                    }
-                },
-                Ok(_) => {}, // 忽略 Ping/Pong 等其他帧
-                Err(e) => {
-                    error!("❌ 等待登录响应时断开: {}", e);
-                    return None;
+            if let Message::Text(text) = msg_res? {
+                match WsRouter::parse(&text) {
+                    Ok(router) if matches!(router.event.as_deref(), Some("login") | Some("error")) => return Ok(router),
+                    _ => warn!("⚠️ 收到非登录响应 (忽略): {}", text),
                 }
             }
         }
+        Err(OkxError::Transport("连接在登录阶段意外关闭".to_string()))
+    }
+}
 
-        error!("❌ 连接在登录阶段意外关闭");
-        None
+/// 🚦 建立底层传输 (直连 / 代理隧道 + 可选 TLS)，WebSocket 与 REST 共用
+/// wss:// / https:// 走 TLS，ws:// / http:// 明文 (本地 Mock / 测试环境)
+pub async fn connect_transport(config: &crate::config::AppConfig, target_url: &Url) -> OkxResult<MaybeTlsStream<TcpStream>> {
+    let use_tls = match target_url.scheme() {
+        "wss" | "https" => true,
+        "ws" | "http" => false,
+        other => return Err(OkxError::Config(format!("不支持的协议 {}://，仅支持 ws / wss / http / https", other))),
+    };
+    let target_host = target_url.host_str()
+        .ok_or_else(|| OkxError::Config(format!("端点地址缺少主机名: {}", target_url)))?;
+    let target_port = target_url.port_or_known_default().unwrap_or(443);
+
+    // ==========================================
+    // 🚦 智能分支：根据是否配置代理决定连接方式
+    // ==========================================
+    let tcp_stream = match &config.proxy {
+        // ➤ 分支 A: 走代理 (本地开发)
+        Some(proxy) => {
+            info!("🔗 [模式] 代理连接: {} -> {}", proxy, target_host);
+            proxy.tunnel(target_host, target_port).await?
+        }
+        // ➤ 分支 B: 直连 (香港/东京服务器)
+        None => {
+            info!("🔗 [模式] 直连 (无代理) -> {}:{}", target_host, target_port);
+            TcpStream::connect(format!("{}:{}", target_host, target_port)).await
+                .map_err(|e| OkxError::Transport(format!("直连失败 (请检查服务器网络): {}", e)))?
+        }
+    };
+    let _ = tcp_stream.set_nodelay(true);
+
+    if use_tls {
+        // 无论直连还是代理，证书都必须校验 (代理路径同样可能被中间人劫持)
+        let cx = TokioTlsConnector::from(config.tls.connector()?);
+        let tls_stream = cx.connect(target_host, tcp_stream).await
+            .map_err(|e| OkxError::Tls(e.to_string()))?;
+        config.tls.verify_pin(&tls_stream)?;
+        Ok(MaybeTlsStream::NativeTls(tls_stream))
+    } else {
+        warn!("⚠️ 使用明文连接 {}，仅限本地测试!", target_url);
+        Ok(MaybeTlsStream::Plain(tcp_stream))
     }
 }
@@ -0,0 +1,176 @@
+// src/okx/correlation.rs
+
+use crate::okx::error::{OkxError, OkxResult};
+use crate::okx::protocol::{OpPacket, WsRouter};
+use crate::okx::trade_data::OrderAck;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+// 超过该时间仍无回执的请求判为超时 (涉及的订单由订单管理器标记超时并补发撤单)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 单笔结果 (批量请求每笔一个)
+#[derive(Debug)]
+pub struct ItemResult {
+    pub cl_ord_id: String,
+    pub ord_id: String,
+    pub result: OkxResult<()>,
+}
+
+/// 📬 一次交易请求的回执
+/// 整包失败 / 超时 / 连接断开时，请求内的每一笔都带上同一个错误
+#[derive(Debug)]
+pub struct OpResult {
+    pub req_id: String,
+    pub op: &'static str,
+    /// 发出到收到回执 (或判定超时) 的耗时
+    pub latency: Duration,
+    pub items: Vec<ItemResult>,
+}
+
+impl OpResult {
+    fn failed(req_id: &str, op: &'static str, latency: Duration, cl_ord_ids: &[String], error: impl Fn() -> OkxError) -> Self {
+        let items = cl_ord_ids.iter()
+            .map(|id| ItemResult { cl_ord_id: id.clone(), ord_id: String::new(), result: Err(error()) })
+            .collect();
+        OpResult { req_id: req_id.to_string(), op, latency, items }
+    }
+}
+
+/// ⏳ 等待某个请求的回执
+pub struct ResponseHandle {
+    pub req_id: String,
+    op: &'static str,
+    cl_ord_ids: Vec<String>,
+    sent_at: Instant,
+    rx: oneshot::Receiver<OpResult>,
+}
+
+impl ResponseHandle {
+    /// 一定会返回：回执到达、超过 REQUEST_TIMEOUT (由 expire 判定) 或连接断开 (每一笔都是超时错误)
+    pub async fn wait(self) -> OpResult {
+        match self.rx.await {
+            Ok(result) => result,
+            Err(_) => OpResult::failed(&self.req_id, self.op, self.sent_at.elapsed(), &self.cl_ord_ids, || {
+                OkxError::Timeout(format!("请求 {} 未收到回执 (连接已断开)", self.req_id))
+            }),
+        }
+    }
+}
+
+struct Pending {
+    op: &'static str,
+    cl_ord_ids: Vec<String>,
+    sent_at: Instant,
+    tx: oneshot::Sender<OpResult>,
+}
+
+/// 📊 回执延迟统计
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AckLatency {
+    pub count: u64,
+    pub last: Duration,
+    pub max: Duration,
+    total: Duration,
+}
+
+impl AckLatency {
+    fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        self.last = latency;
+        self.max = self.max.max(latency);
+        self.total += latency;
+    }
+
+    pub fn avg(&self) -> Duration {
+        if self.count == 0 { Duration::ZERO } else { self.total / self.count as u32 }
+    }
+}
+
+/// 🔗 [请求关联] 私有连接上每个交易请求按 id 登记，回执到达时配对
+pub struct RequestTracker {
+    pending: HashMap<String, Pending>,
+    latency: AckLatency,
+}
+
+impl Default for RequestTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestTracker {
+    pub fn new() -> Self {
+        RequestTracker { pending: HashMap::new(), latency: AckLatency::default() }
+    }
+
+    /// 📝 登记即将发出的请求
+    pub fn register(&mut self, packet: &OpPacket) -> ResponseHandle {
+        let (tx, rx) = oneshot::channel();
+        let cl_ord_ids: Vec<String> = packet.targets.iter().map(|t| t.cl_ord_id.clone()).collect();
+        let sent_at = Instant::now();
+        self.pending.insert(packet.req_id.clone(), Pending { op: packet.op, cl_ord_ids: cl_ord_ids.clone(), sent_at, tx });
+        ResponseHandle { req_id: packet.req_id.clone(), op: packet.op, cl_ord_ids, sent_at, rx }
+    }
+
+    /// 📨 回执配对：记录延迟，逐笔 sCode 转为类型化错误，唤醒等待方
+    pub fn resolve(&mut self, router: &WsRouter) {
+        let Some(req_id) = router.id.as_deref() else { return };
+        let Some(pending) = self.pending.remove(req_id) else {
+            warn!("⚠️ [回执] {:?} {} 无对应请求 (可能已超时)", router.op, req_id);
+            return;
+        };
+
+        let latency = pending.sent_at.elapsed();
+        self.latency.record(latency);
+        info!(
+            "⚡ [回执] {} {} 延迟 {}ms (均值 {}ms / 最大 {}ms)",
+            pending.op, req_id, latency.as_millis(), self.latency.avg().as_millis(), self.latency.max.as_millis()
+        );
+
+        let acks: Vec<OrderAck> = router.data.as_deref()
+            .and_then(|raw| serde_json::from_str(raw.get()).ok())
+            .unwrap_or_default();
+        let code = router.code.as_deref().unwrap_or("0");
+        let result = if acks.is_empty() && code != "0" {
+            // 整包失败且没有逐笔结果 (如参数错误)，请求内的每一笔都算失败
+            let msg = router.msg.as_deref().unwrap_or_default();
+            if pending.cl_ord_ids.is_empty() {
+                error!("❌ [{}] 请求失败: [{}] {}", pending.op, code, msg);
+            }
+            OpResult::failed(req_id, pending.op, latency, &pending.cl_ord_ids, || OkxError::from_api(code, msg, false))
+        } else {
+            let items = acks.into_iter().enumerate().filter_map(|(i, ack)| {
+                // 回执按请求顺序返回，clOrdId 缺失时按位置对应
+                let cl_ord_id = if ack.client_oid.is_empty() { pending.cl_ord_ids.get(i)?.clone() } else { ack.client_oid };
+                Some(ItemResult {
+                    result: if ack.s_code == "0" { Ok(()) } else { Err(OkxError::from_item(&ack.s_code, &ack.s_msg)) },
+                    cl_ord_id,
+                    ord_id: ack.ord_id,
+                })
+            }).collect();
+            OpResult { req_id: req_id.to_string(), op: pending.op, latency, items }
+        };
+        // 等待方可能已经放弃，发送失败无需处理
+        let _ = pending.tx.send(result);
+    }
+
+    /// ⏰ 长时间无回执的请求判为超时，等待方收到逐笔超时错误
+    pub fn expire(&mut self) {
+        let expired: Vec<String> = self.pending.iter()
+            .filter(|(_, p)| p.sent_at.elapsed() > REQUEST_TIMEOUT)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for req_id in expired {
+            let Some(p) = self.pending.remove(&req_id) else { continue };
+            warn!("⏰ [回执] {} {} 超过 {}s 未响应", p.op, req_id, REQUEST_TIMEOUT.as_secs());
+            let result = OpResult::failed(&req_id, p.op, p.sent_at.elapsed(), &p.cl_ord_ids, || {
+                OkxError::Timeout(format!("请求 {} 超过 {}s 未响应", req_id, REQUEST_TIMEOUT.as_secs()))
+            });
+            let _ = p.tx.send(result);
+        }
+    }
+}
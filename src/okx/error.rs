@@ -0,0 +1,196 @@
+// src/okx/error.rs
+
+use tokio_tungstenite::tungstenite;
+
+/// ⚠️ [错误分类] okx 模块统一错误类型
+/// 上层 (守护者 / 策略) 按类别决定: 重试、退避加长，还是直接停机
+#[derive(Debug, thiserror::Error)]
+pub enum OkxError {
+    /// 配置错误 (端点地址非法等)，重试无意义
+    #[error("配置错误: {0}")]
+    Config(String),
+
+    /// TCP 连接 / WebSocket 读写失败
+    #[error("网络传输错误: {0}")]
+    Transport(String),
+
+    #[error("TLS 错误: {0}")]
+    Tls(String),
+
+    #[error("代理错误: {0}")]
+    Proxy(String),
+
+    /// WebSocket 升级握手失败
+    #[error("WebSocket 握手失败: {0}")]
+    Handshake(#[source] Box<tungstenite::Error>),
+
+    /// 登录鉴权被拒绝 (code 为 OKX 错误码)
+    #[error("鉴权失败 [{code}] {kind}: {msg}")]
+    Auth { kind: AuthFailure, code: String, msg: String },
+
+    /// 等待响应超时 (登录 / 请求确认)
+    #[error("等待超时: {0}")]
+    Timeout(String),
+
+    /// 触发 OKX 限频
+    #[error("触发限频 [{code}]: {msg}")]
+    RateLimit { code: String, msg: String },
+
+    /// 其他业务错误 (订阅失败 / 下单被拒等)
+    #[error("OKX 返回错误 [{code}]: {msg}")]
+    Api { code: String, msg: String },
+
+    /// 交易请求的单笔结果被拒 (sCode 非 0)
+    #[error("订单被拒 [{code}] {kind}: {msg}")]
+    Rejected { kind: RejectReason, code: String, msg: String },
+
+    /// 本地校验不通过的订单 (低于最小下单量 / 产品不可交易等)，不会发往交易所
+    #[error("订单参数无效: {0}")]
+    InvalidOrder(String),
+
+    /// JSON 解析 / 序列化失败
+    #[error("协议解析失败: {0}")]
+    Protocol(#[from] serde_json::Error),
+}
+
+pub type OkxResult<T> = Result<T, OkxError>;
+
+/// 🔑 登录被拒的具体原因 (WS 60xxx 与 REST 501xx 两套错误码都会出现)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFailure {
+    WrongPassphrase,
+    /// 时间戳无效或已过期 (通常是本机时钟漂移，校时后可重试)
+    TimestampExpired,
+    IpNotWhitelisted,
+    InvalidKey,
+    InvalidSign,
+    /// 模拟盘 Key 连实盘 (或反之)
+    EnvironmentMismatch,
+    Other,
+}
+
+impl AuthFailure {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "60024" | "50105" => AuthFailure::WrongPassphrase,
+            "60004" | "60006" | "50102" | "50112" => AuthFailure::TimestampExpired,
+            "50110" => AuthFailure::IpNotWhitelisted,
+            "60005" | "60032" | "50111" | "50119" => AuthFailure::InvalidKey,
+            "60007" | "50113" => AuthFailure::InvalidSign,
+            "50101" => AuthFailure::EnvironmentMismatch,
+            _ => AuthFailure::Other,
+        }
+    }
+
+    /// 只有时间戳问题值得重试，其余都需要人工修改配置
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AuthFailure::TimestampExpired)
+    }
+}
+
+impl std::fmt::Display for AuthFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            AuthFailure::WrongPassphrase => "Passphrase 错误",
+            AuthFailure::TimestampExpired => "时间戳过期 (请检查本机时钟)",
+            AuthFailure::IpNotWhitelisted => "IP 不在 API Key 白名单内",
+            AuthFailure::InvalidKey => "API Key 无效",
+            AuthFailure::InvalidSign => "签名错误 (请检查 Secret Key)",
+            AuthFailure::EnvironmentMismatch => "API Key 与实盘/模拟盘环境不匹配",
+            AuthFailure::Other => "登录失败",
+        };
+        f.write_str(text)
+    }
+}
+
+/// 🚫 交易请求单笔被拒的常见原因 (sCode)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    InsufficientBalance,
+    BelowMinSize,
+    PriceOutOfRange,
+    DuplicateClOrdId,
+    /// 订单不存在 (撤单 / 改单时)
+    OrderNotFound,
+    /// 订单已成交或已撤销，无法再撤 / 改
+    AlreadyFinished,
+    Other,
+}
+
+impl RejectReason {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "51008" | "51119" | "51127" | "51131" => RejectReason::InsufficientBalance,
+            "51020" => RejectReason::BelowMinSize,
+            "51006" | "51137" | "51138" => RejectReason::PriceOutOfRange,
+            "51016" => RejectReason::DuplicateClOrdId,
+            "51603" => RejectReason::OrderNotFound,
+            "51400" | "51401" | "51503" => RejectReason::AlreadyFinished,
+            _ => RejectReason::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            RejectReason::InsufficientBalance => "余额不足",
+            RejectReason::BelowMinSize => "低于最小下单量",
+            RejectReason::PriceOutOfRange => "价格超出限价范围",
+            RejectReason::DuplicateClOrdId => "clOrdId 重复",
+            RejectReason::OrderNotFound => "订单不存在",
+            RejectReason::AlreadyFinished => "订单已成交或已撤销",
+            RejectReason::Other => "请求被拒",
+        };
+        f.write_str(text)
+    }
+}
+
+// OKX 限频相关错误码
+const RATE_LIMIT_CODES: &[&str] = &["50011", "50061", "60014"];
+
+impl OkxError {
+    /// 根据 OKX 返回的 code 分类 (登录阶段的错误一律视为鉴权失败)
+    pub fn from_api(code: &str, msg: &str, during_login: bool) -> Self {
+        let (code, msg) = (code.to_string(), msg.to_string());
+        if RATE_LIMIT_CODES.contains(&code.as_str()) {
+            OkxError::RateLimit { code, msg }
+        } else if during_login {
+            OkxError::Auth { kind: AuthFailure::from_code(&code), code, msg }
+        } else {
+            OkxError::Api { code, msg }
+        }
+    }
+
+    /// 交易请求的逐笔结果 (sCode/sMsg) 分类
+    pub fn from_item(code: &str, msg: &str) -> Self {
+        if RATE_LIMIT_CODES.contains(&code) {
+            return OkxError::RateLimit { code: code.to_string(), msg: msg.to_string() };
+        }
+        OkxError::Rejected { kind: RejectReason::from_code(code), code: code.to_string(), msg: msg.to_string() }
+    }
+
+    /// OKX 错误码与信息 (本地产生的错误没有错误码)
+    pub fn code_and_msg(&self) -> (String, String) {
+        match self {
+            OkxError::Auth { code, msg, .. } | OkxError::RateLimit { code, msg }
+            | OkxError::Api { code, msg } | OkxError::Rejected { code, msg, .. } => (code.clone(), msg.clone()),
+            other => (String::new(), other.to_string()),
+        }
+    }
+
+    /// 致命错误: 重连也无法恢复，应停机人工介入
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            OkxError::Config(_) => true,
+            OkxError::Auth { kind, .. } => !kind.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for OkxError {
+    fn from(e: tungstenite::Error) -> Self {
+        OkxError::Transport(e.to_string())
+    }
+}
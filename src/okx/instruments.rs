@@ -0,0 +1,51 @@
+// src/okx/instruments.rs
+
+use crate::okx::error::OkxResult;
+use crate::okx::market_data::Instrument;
+use crate::okx::protocol::InstType;
+use crate::okx::rest::RestClient;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// 📚 [产品注册表] 启动时从 REST 全量加载，之后由 instruments 频道推送增量刷新
+/// (tickSz / lotSz / 状态等变更会通过该频道推送)
+pub struct InstrumentRegistry {
+    by_id: RwLock<HashMap<String, Instrument>>,
+}
+
+impl InstrumentRegistry {
+    /// 📥 拉取指定产品类型的全部产品
+    pub async fn load(rest: &RestClient, inst_types: &[InstType]) -> OkxResult<Self> {
+        let mut by_id = HashMap::new();
+        for inst_type in inst_types {
+            let list: Vec<Instrument> = rest.get(&format!("/api/v5/public/instruments?instType={}", inst_type.as_str())).await?;
+            info!("📐 [产品] 已加载 {} 个 {} 产品", list.len(), inst_type.as_str());
+            by_id.extend(list.into_iter().map(|i| (i.inst_id.clone(), i)));
+        }
+        Ok(InstrumentRegistry { by_id: RwLock::new(by_id) })
+    }
+
+    pub fn get(&self, inst_id: &str) -> Option<Instrument> {
+        self.by_id.read().unwrap().get(inst_id).cloned()
+    }
+
+    /// 🔄 instruments 频道推送：新增或覆盖，状态变化单独提示
+    pub fn apply(&self, updates: Vec<Instrument>) {
+        let mut by_id = self.by_id.write().unwrap();
+        for inst in updates {
+            if let Some(old) = by_id.get(&inst.inst_id) {
+                if old.state != inst.state {
+                    warn!("🚦 [产品] {} 状态变化: {:?} -> {:?}", inst.inst_id, old.state, inst.state);
+                }
+                if old.tick_sz != inst.tick_sz || old.lot_sz != inst.lot_sz || old.min_sz != inst.min_sz {
+                    warn!(
+                        "📐 [产品] {} 精度变化: tickSz {} -> {} | lotSz {} -> {} | minSz {} -> {}",
+                        inst.inst_id, old.tick_sz, inst.tick_sz, old.lot_sz, inst.lot_sz, old.min_sz, inst.min_sz
+                    );
+                }
+            }
+            by_id.insert(inst.inst_id.clone(), inst);
+        }
+    }
+}
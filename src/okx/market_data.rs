@@ -1,3 +1,5 @@
+use crate::okx::error::{OkxError, OkxResult};
+use crate::okx::protocol::{InstType, Side};
 use serde::{Deserialize, Deserializer, Serialize};
 
 /// 📈 [Market Domain] Ticker 数据
@@ -24,6 +26,83 @@ pub struct Ticker {
     pub ts: String, // 时间戳保留字符串，避免精度问题，按需转换
 }
 
+/// 📚 [Market Domain] 深度推送 (books / books5 / bbo-tbt / books-l2-tbt 同构)
+/// 档位格式: [价格, 数量, 已废弃, 订单数]，保留原始字符串用于 checksum 计算
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BookData {
+    #[serde(default)]
+    pub asks: Vec<Vec<String>>,
+    #[serde(default)]
+    pub bids: Vec<Vec<String>>,
+    #[serde(rename = "instId", default)]
+    pub inst_id: Option<String>,
+    pub ts: String,
+    // 仅 books / books-l2-tbt 携带
+    #[serde(default)]
+    pub checksum: Option<i64>,
+    #[serde(rename = "prevSeqId", default)]
+    pub prev_seq_id: Option<i64>,
+    #[serde(rename = "seqId", default)]
+    pub seq_id: Option<i64>,
+}
+
+/// 💱 [Market Domain] 公共成交 (trades 频道 / REST 历史成交同构)
+/// trades 会把同一主动单的多笔成交合并为一条，count 为合并笔数 (缺省按 1 笔)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Trade {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "tradeId")]
+    pub trade_id: String,
+    #[serde(deserialize_with = "parse_f64_from_string")]
+    pub px: f64,
+    #[serde(deserialize_with = "parse_f64_from_string")]
+    pub sz: f64,
+    /// 主动成交方向 (taker)
+    pub side: Side,
+    pub ts: String,
+    #[serde(default)]
+    pub count: Option<String>,
+}
+
+impl Trade {
+    /// 实际成交笔数 (聚合推送按 count 计，不带 count 的按 1 笔)
+    pub fn trade_count(&self) -> u64 {
+        self.count.as_deref().and_then(|c| c.parse().ok()).unwrap_or(1)
+    }
+}
+
+/// 🕯️ [Market Domain] K 线 (candle* 频道推送 / REST 历史 K 线 / 本地合成)
+/// OKX 格式: [ts, o, h, l, c, vol, volCcy, volCcyQuote, confirm]，全部为字符串
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    pub ts: i64, // 开盘时间
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub vol: f64,       // 成交量 (现货为基础币)
+    pub vol_quote: f64, // 成交额 (计价币)
+    pub confirmed: bool, // 是否已收盘
+}
+
+impl Candle {
+    /// 解析一行 OKX K 线 (字段缺失或非数字返回 None)
+    pub fn from_row(row: &[String]) -> Option<Self> {
+        let num = |i: usize| row.get(i).and_then(|v| v.parse::<f64>().ok());
+        Some(Candle {
+            ts: row.first()?.parse().ok()?,
+            open: num(1)?,
+            high: num(2)?,
+            low: num(3)?,
+            close: num(4)?,
+            vol: num(5)?,
+            vol_quote: num(7).or_else(|| num(6)).unwrap_or(0.0),
+            confirmed: row.get(8).is_none_or(|c| c == "1"),
+        })
+    }
+}
+
 /// 🛠️ [Helper] 自定义反序列化函数
 /// 解决 OKX API 返回 {"last": "123.45"} 这种将数字包在字符串里的问题
 /// 直接 parse 避免 String 内存分配
@@ -35,3 +114,254 @@ where
     let s: String = Deserialize::deserialize(deserializer)?;
     s.parse::<f64>().map_err(serde::de::Error::custom)
 }
+
+/// 🛠️ [Helper] 可能为空字符串的数值字段 (如现货的 ctVal)
+fn parse_opt_f64_from_string<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    if s.is_empty() {
+        return Ok(None);
+    }
+    s.parse::<f64>().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// 📏 精度步长 (tickSz / lotSz)：数值用于取整，保留原始字符串用于确定小数位
+/// 小数位从字符串数出 (0.25 -> 2)，不从 f64 反推，避免 0.25 这类步长被算少
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Step {
+    pub value: f64,
+    raw: String,
+}
+
+impl Step {
+    /// 小数位数 (0.0001 -> 4, 0.25 -> 2, 1 -> 0)
+    pub fn decimals(&self) -> usize {
+        decimals_of(&self.raw)
+    }
+}
+
+impl TryFrom<String> for Step {
+    type Error = std::num::ParseFloatError;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        Ok(Step { value: raw.parse()?, raw })
+    }
+}
+
+impl From<Step> for String {
+    fn from(step: Step) -> Self {
+        step.raw
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// 🚦 产品状态
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstState {
+    Live,
+    Suspend,
+    Preopen,
+    Test,
+    #[serde(other)]
+    Unknown,
+}
+
+/// 📐 [Market Domain] 交易产品元数据 (/api/v5/public/instruments 与 instruments 频道同构)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Instrument {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+
+    #[serde(rename = "instType")]
+    pub inst_type: InstType,
+
+    pub state: InstState,
+
+    // 价格精度 (价格必须是 tickSz 的整数倍)
+    #[serde(rename = "tickSz")]
+    pub tick_sz: Step,
+
+    // 下单数量精度 (数量必须是 lotSz 的整数倍)
+    #[serde(rename = "lotSz")]
+    pub lot_sz: Step,
+
+    // 最小下单数量
+    #[serde(rename = "minSz", deserialize_with = "parse_f64_from_string")]
+    pub min_sz: f64,
+
+    // 合约面值 (仅衍生品，现货为空)
+    #[serde(rename = "ctVal", default, deserialize_with = "parse_opt_f64_from_string")]
+    pub ct_val: Option<f64>,
+
+    #[serde(rename = "ctValCcy", default)]
+    pub ct_val_ccy: String,
+}
+
+impl Instrument {
+    pub fn is_tradable(&self) -> bool {
+        self.state == InstState::Live
+    }
+
+    /// 价格按 tickSz 取整：买单向下、卖单向上，保证不会比预期价格更差
+    pub fn round_price(&self, px: f64, side: Side) -> f64 {
+        let tick_sz = self.tick_sz.value;
+        if tick_sz <= 0.0 {
+            return px;
+        }
+        let ticks = px / tick_sz;
+        let ticks = if side == Side::Buy { (ticks + 1e-9).floor() } else { (ticks - 1e-9).ceil() };
+        ticks * tick_sz
+    }
+
+    /// 数量向下取整到 lotSz 的整数倍 (卖出时绝不能超过实际持有)
+    pub fn floor_to_lot(&self, qty: f64) -> f64 {
+        let lot_sz = self.lot_sz.value;
+        if lot_sz <= 0.0 {
+            return qty;
+        }
+        // 加一点余量，避免 0.3 / 0.1 = 2.9999999 这类浮点误差少卖一个 lot
+        (qty / lot_sz + 1e-9).floor() * lot_sz
+    }
+
+    /// 取整后的下单数量；低于最小下单量直接拒绝，不浪费一次请求
+    pub fn check_size(&self, qty: f64) -> OkxResult<f64> {
+        let size = self.floor_to_lot(qty);
+        if size < self.min_sz.max(self.lot_sz.value) {
+            return Err(OkxError::InvalidOrder(format!(
+                "{} 数量 {} 低于最小下单量 {}", self.inst_id, qty, self.min_sz
+            )));
+        }
+        Ok(size)
+    }
+
+    /// 一张合约折合多少基础币
+    /// 正向合约 (BTC-USDT-SWAP) 面值以基础币计；反向合约 (BTC-USD-SWAP) 面值以计价币计，需要参考价换算
+    pub fn contract_value_in_base(&self, ref_px: Option<f64>) -> OkxResult<f64> {
+        let ct_val = self.ct_val
+            .filter(|v| *v > 0.0)
+            .ok_or_else(|| OkxError::InvalidOrder(format!("{} 缺少合约面值", self.inst_id)))?;
+        let quote_ccy = self.inst_id.split('-').nth(1).unwrap_or_default();
+        if self.ct_val_ccy != quote_ccy {
+            return Ok(ct_val);
+        }
+        match ref_px {
+            Some(px) if px > 0.0 => Ok(ct_val / px),
+            _ => Err(OkxError::InvalidOrder(format!("{} 为反向合约，换算张数需要参考价", self.inst_id))),
+        }
+    }
+
+    /// 按 lotSz 的小数位格式化数量
+    pub fn format_size(&self, qty: f64) -> String {
+        format!("{:.*}", self.lot_sz.decimals(), qty)
+    }
+
+    /// 按 tickSz 的小数位格式化价格
+    pub fn format_price(&self, px: f64) -> String {
+        format!("{:.*}", self.tick_sz.decimals(), px)
+    }
+}
+
+/// 精度步长字符串的小数位数 (末尾的 0 不计，"0.10" -> 1)
+fn decimals_of(raw: &str) -> usize {
+    raw.split_once('.').map_or(0, |(_, frac)| frac.trim_end_matches('0').len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn inst(inst_id: &str, inst_type: &str, tick_sz: &str, lot_sz: &str, min_sz: &str, ct_val: &str, ct_val_ccy: &str) -> Instrument {
+        serde_json::from_value(json!({
+            "instId": inst_id, "instType": inst_type, "state": "live",
+            "tickSz": tick_sz, "lotSz": lot_sz, "minSz": min_sz, "ctVal": ct_val, "ctValCcy": ct_val_ccy,
+        })).unwrap()
+    }
+
+    #[test]
+    fn check_size_floors_to_lot_and_enforces_min() {
+        let spot = inst("SOL-USDT", "SPOT", "0.01", "0.001", "0.01", "", "");
+        let cases = [
+            (0.0129, Some(0.012)),
+            (0.3, Some(0.3)), // 0.3 / 0.001 浮点误差不应少一个 lot
+            (0.01, Some(0.01)),
+            (0.0099, None),
+            (0.0, None),
+        ];
+        for (qty, expected) in cases {
+            let got = spot.check_size(qty).ok();
+            assert!(match (got, expected) {
+                (Some(g), Some(e)) => (g - e).abs() < 1e-12,
+                (None, None) => true,
+                _ => false,
+            }, "qty {} -> {:?}", qty, got);
+        }
+
+        // minSz 小于 lotSz 时以 lotSz 为准
+        let swap = inst("BTC-USDT-SWAP", "SWAP", "0.1", "1", "0.1", "0.01", "BTC");
+        assert!(swap.check_size(0.5).is_err());
+        assert_eq!(swap.check_size(2.7).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn contract_value_in_base() {
+        let linear = inst("BTC-USDT-SWAP", "SWAP", "0.1", "0.01", "0.01", "0.01", "BTC");
+        assert_eq!(linear.contract_value_in_base(None).unwrap(), 0.01);
+        assert_eq!(linear.contract_value_in_base(Some(50_000.0)).unwrap(), 0.01);
+
+        let inverse = inst("BTC-USD-SWAP", "SWAP", "0.1", "1", "1", "100", "USD");
+        assert_eq!(inverse.contract_value_in_base(Some(50_000.0)).unwrap(), 0.002);
+        assert!(inverse.contract_value_in_base(None).is_err());
+        assert!(inverse.contract_value_in_base(Some(0.0)).is_err());
+
+        let spot = inst("BTC-USDT", "SPOT", "0.1", "0.0001", "0.0001", "", "");
+        assert!(spot.contract_value_in_base(Some(50_000.0)).is_err());
+    }
+
+    #[test]
+    fn decimals_come_from_raw_step() {
+        for (raw, decimals) in [("0.0001", 4), ("0.25", 2), ("0.5", 1), ("0.10", 1), ("1", 0), ("10", 0), ("0.00000001", 8)] {
+            let step = Step::try_from(raw.to_string()).unwrap();
+            assert_eq!(step.decimals(), decimals, "{}", raw);
+            assert_eq!(step.to_string(), raw);
+        }
+        assert!(Step::try_from("abc".to_string()).is_err());
+    }
+
+    #[test]
+    fn round_price_never_worse_than_requested() {
+        let eth = inst("ETH-USDT", "SPOT", "0.25", "0.001", "0.001", "", "");
+        let cases = [
+            (100.3, Side::Buy, 100.25),
+            (100.3, Side::Sell, 100.5),
+            (100.5, Side::Buy, 100.5), // 恰好在刻度上不动
+            (100.5, Side::Sell, 100.5),
+        ];
+        for (px, side, expected) in cases {
+            assert!((eth.round_price(px, side) - expected).abs() < 1e-9, "{} {:?}", px, side);
+        }
+        let coarse = inst("BTC-USDT", "SPOT", "0.1", "0.00000001", "0.00001", "", "");
+        assert_eq!(coarse.format_price(coarse.round_price(0.3, Side::Buy)), "0.3");
+    }
+
+    #[test]
+    fn format_uses_step_decimals() {
+        let eth = inst("ETH-USDT", "SPOT", "0.25", "0.001", "0.001", "", "");
+        assert_eq!(eth.format_price(100.25), "100.25");
+        assert_eq!(eth.format_price(100.0), "100.00");
+        assert_eq!(eth.format_size(0.1234), "0.123");
+
+        let whole = inst("BTC-USD-SWAP", "SWAP", "0.5", "1", "1", "100", "USD");
+        assert_eq!(whole.format_price(30000.5), "30000.5");
+        assert_eq!(whole.format_size(3.0), "3");
+    }
+}
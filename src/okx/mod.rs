@@ -1,5 +1,20 @@
 pub mod auth;
 pub mod client;
+pub mod error;
+pub mod proxy;
+pub mod rest;
+pub mod tls;
+pub mod subscription;
+pub mod order_id;
+pub mod order_manager;
+pub mod correlation;
+pub mod instruments;
+pub mod order_book;
+pub mod trade_tape;
+pub mod bar_builder;
+pub mod backfill;
+pub mod supervisor;
+pub mod watchdog;
 
 pub mod protocol;
 
@@ -0,0 +1,520 @@
+// src/okx/order_book.rs
+
+use crate::okx::market_data::BookData;
+use crate::okx::protocol::{Side, WsArg};
+use log::{info, error};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+
+// OKX checksum 只覆盖买卖各前 25 档
+const CHECKSUM_DEPTH: usize = 25;
+
+/// 价格键：f64 按 total_cmp 排序 (推送中不会出现 NaN)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Px(f64);
+
+impl Eq for Px {}
+
+impl PartialOrd for Px {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Px {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 单个价位 (保留原始字符串，checksum 必须按推送原文计算)
+#[derive(Debug, Clone)]
+struct Level {
+    px: String,
+    sz: String,
+    size: f64,
+}
+
+/// ⚠️ 本地深度失效的原因，出现后必须重新订阅拿新快照
+#[derive(Debug, Clone)]
+pub enum BookFault {
+    /// 还没收到快照就来了增量
+    NoSnapshot,
+    SequenceGap { expected: i64, got: i64 },
+    ChecksumMismatch { expected: i64, actual: i64 },
+    Malformed(String),
+}
+
+impl std::fmt::Display for BookFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookFault::NoSnapshot => write!(f, "缺少全量快照"),
+            BookFault::SequenceGap { expected, got } => write!(f, "序号不连续 (期望 prevSeqId={}, 实际 {})", expected, got),
+            BookFault::ChecksumMismatch { expected, actual } => write!(f, "checksum 不一致 (推送 {}, 本地 {})", expected, actual),
+            BookFault::Malformed(e) => write!(f, "档位格式错误: {}", e),
+        }
+    }
+}
+
+/// 📏 中间价上下一定范围内的挂单量
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Depth {
+    pub bid_qty: f64,
+    pub bid_notional: f64,
+    pub ask_qty: f64,
+    pub ask_notional: f64,
+}
+
+/// 🧹 吃掉指定金额的预估结果
+#[derive(Debug, Clone, Copy)]
+pub struct SweepEstimate {
+    pub best_px: f64,
+    /// 成交量加权均价
+    pub avg_px: f64,
+    /// 最后吃到的价位
+    pub worst_px: f64,
+    pub filled_qty: f64,
+    pub filled_notional: f64,
+    /// 深度不足以吃满目标金额
+    pub complete: bool,
+}
+
+impl SweepEstimate {
+    /// 相对最优价的滑点 (正数表示比最优价更差)
+    pub fn slippage(&self) -> f64 {
+        if self.best_px <= 0.0 {
+            return 0.0;
+        }
+        ((self.avg_px - self.best_px) / self.best_px).abs()
+    }
+}
+
+/// 📖 单个品种的本地深度
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    bids: BTreeMap<Px, Level>,
+    asks: BTreeMap<Px, Level>,
+    seq_id: Option<i64>,
+    pub ts: i64,
+}
+
+impl OrderBook {
+    fn new() -> Self {
+        OrderBook { bids: BTreeMap::new(), asks: BTreeMap::new(), seq_id: None, ts: 0 }
+    }
+
+    fn apply_snapshot(&mut self, data: &BookData) -> Result<(), BookFault> {
+        self.bids.clear();
+        self.asks.clear();
+        Self::apply_levels(&mut self.bids, &data.bids)?;
+        Self::apply_levels(&mut self.asks, &data.asks)?;
+        self.seq_id = data.seq_id;
+        self.ts = data.ts.parse().unwrap_or(0);
+        self.verify(data.checksum)
+    }
+
+    fn apply_update(&mut self, data: &BookData) -> Result<(), BookFault> {
+        // prevSeqId 必须等于上一条的 seqId (无变化的心跳推送两者相同)
+        if let (Some(last), Some(prev)) = (self.seq_id, data.prev_seq_id) {
+            if prev != last {
+                return Err(BookFault::SequenceGap { expected: last, got: prev });
+            }
+        }
+        Self::apply_levels(&mut self.bids, &data.bids)?;
+        Self::apply_levels(&mut self.asks, &data.asks)?;
+        self.seq_id = data.seq_id.or(self.seq_id);
+        self.ts = data.ts.parse().unwrap_or(self.ts);
+        self.verify(data.checksum)
+    }
+
+    /// 数量为 0 表示删除该价位
+    fn apply_levels(side: &mut BTreeMap<Px, Level>, levels: &[Vec<String>]) -> Result<(), BookFault> {
+        for level in levels {
+            let (Some(px), Some(sz)) = (level.first(), level.get(1)) else {
+                return Err(BookFault::Malformed(format!("{:?}", level)));
+            };
+            let price = px.parse::<f64>().map_err(|e| BookFault::Malformed(format!("{} ({})", px, e)))?;
+            let size = sz.parse::<f64>().map_err(|e| BookFault::Malformed(format!("{} ({})", sz, e)))?;
+            if size == 0.0 {
+                side.remove(&Px(price));
+            } else {
+                side.insert(Px(price), Level { px: px.clone(), sz: sz.clone(), size });
+            }
+        }
+        Ok(())
+    }
+
+    fn verify(&self, checksum: Option<i64>) -> Result<(), BookFault> {
+        let Some(expected) = checksum else { return Ok(()) };
+        let actual = self.checksum() as i64;
+        if actual != expected {
+            return Err(BookFault::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// OKX 算法: 买卖前 25 档交替拼接 "bidPx:bidSz:askPx:askSz:..."，取 CRC32 的有符号值
+    fn checksum(&self) -> i32 {
+        let mut bids = self.bids.values().rev();
+        let mut asks = self.asks.values();
+        let mut parts = Vec::with_capacity(CHECKSUM_DEPTH * 4);
+        for _ in 0..CHECKSUM_DEPTH {
+            if let Some(b) = bids.next() {
+                parts.push(b.px.as_str());
+                parts.push(b.sz.as_str());
+            }
+            if let Some(a) = asks.next() {
+                parts.push(a.px.as_str());
+                parts.push(a.sz.as_str());
+            }
+        }
+        crc32fast::hash(parts.join(":").as_bytes()) as i32
+    }
+
+    /// 最优 n 档 (价格, 数量)；Buy 为买盘从高到低，Sell 为卖盘从低到高
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn levels(&self, side: Side, n: usize) -> Vec<(f64, f64)> {
+        match side {
+            Side::Buy => self.bids.iter().rev().take(n).map(|(p, l)| (p.0, l.size)).collect(),
+            Side::Sell => self.asks.iter().take(n).map(|(p, l)| (p.0, l.size)).collect(),
+        }
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        let bid = self.bids.keys().next_back()?.0;
+        let ask = self.asks.keys().next()?.0;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// 中间价上下 pct (0.01 = 1%) 范围内的挂单量
+    pub fn depth_within(&self, pct: f64) -> Option<Depth> {
+        let mid = self.mid()?;
+        let (low, high) = (mid * (1.0 - pct), mid * (1.0 + pct));
+        let mut depth = Depth::default();
+        for (px, level) in self.bids.range(Px(low)..).rev() {
+            depth.bid_qty += level.size;
+            depth.bid_notional += level.size * px.0;
+        }
+        for (px, level) in self.asks.range(..=Px(high)) {
+            depth.ask_qty += level.size;
+            depth.ask_notional += level.size * px.0;
+        }
+        Some(depth)
+    }
+
+    /// 吃单方向的价位：买入吃卖盘 (从低到高)，卖出吃买盘 (从高到低)
+    fn taker_levels(&self, side: Side) -> Box<dyn Iterator<Item = (&Px, &Level)> + '_> {
+        match side {
+            Side::Buy => Box::new(self.asks.iter()),
+            Side::Sell => Box::new(self.bids.iter().rev()),
+        }
+    }
+
+    /// 按金额 (计价币) 逐档吃单的预估：买入吃卖盘，卖出吃买盘
+    pub fn sweep(&self, side: Side, notional: f64) -> Option<SweepEstimate> {
+        let levels = self.taker_levels(side);
+        let mut est: Option<SweepEstimate> = None;
+        let mut remaining = notional;
+        for (px, level) in levels {
+            let e = est.get_or_insert(SweepEstimate {
+                best_px: px.0, avg_px: px.0, worst_px: px.0, filled_qty: 0.0, filled_notional: 0.0, complete: false,
+            });
+            let take = (level.size * px.0).min(remaining);
+            e.filled_notional += take;
+            e.filled_qty += take / px.0;
+            e.worst_px = px.0;
+            remaining -= take;
+            if remaining <= 0.0 {
+                e.complete = true;
+                break;
+            }
+        }
+        let mut e = est?;
+        if e.filled_qty > 0.0 {
+            e.avg_px = e.filled_notional / e.filled_qty;
+        }
+        Some(e)
+    }
+
+    /// 📐 均价滑点不超过 max_slippage 时最多能吃下的金额 (计价币)
+    /// 均价上限 L = 最优价 × (1 ± 预算)；整档吃下仍满足则继续，否则只取该档的一部分 x:
+    /// (N + x) / (Q + x / p) = L  =>  x = (L·Q - N) / (1 - L / p)
+    pub fn max_notional(&self, side: Side, max_slippage: f64) -> f64 {
+        let mut levels = self.taker_levels(side).peekable();
+        let Some((best, _)) = levels.peek() else { return 0.0 };
+        let limit = match side {
+            Side::Buy => best.0 * (1.0 + max_slippage),
+            Side::Sell => best.0 * (1.0 - max_slippage),
+        };
+        let (mut notional, mut qty) = (0.0, 0.0);
+        for (px, level) in levels {
+            let level_notional = level.size * px.0;
+            let within = match side {
+                Side::Buy => (notional + level_notional) / (qty + level.size) <= limit,
+                Side::Sell => (notional + level_notional) / (qty + level.size) >= limit,
+            };
+            if within {
+                notional += level_notional;
+                qty += level.size;
+            } else {
+                let partial = (limit * qty - notional) / (1.0 - limit / px.0);
+                return notional + partial.clamp(0.0, level_notional);
+            }
+        }
+        notional
+    }
+}
+
+/// 📚 [本地深度] 所有订阅品种的 L2 深度
+/// 快照 + 增量合并，seqId 断档或 checksum 不一致时丢弃该品种并排队重新订阅
+pub struct OrderBooks {
+    books: RwLock<HashMap<String, OrderBook>>,
+    // 等待新快照的品种 (期间的增量直接忽略)
+    resyncing: Mutex<HashSet<String>>,
+    resync_queue: Mutex<Vec<WsArg>>,
+}
+
+impl Default for OrderBooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBooks {
+    pub fn new() -> Self {
+        OrderBooks {
+            books: RwLock::new(HashMap::new()),
+            resyncing: Mutex::new(HashSet::new()),
+            resync_queue: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 📥 深度推送：action=update 为增量，其余 (snapshot / 无 action 的全量频道) 一律按快照处理
+    pub fn on_push(&self, arg: &WsArg, action: Option<&str>, data: Vec<BookData>) {
+        for d in data {
+            let Some(inst_id) = arg.inst_id.clone().or(d.inst_id.clone()) else { continue };
+            let is_update = action == Some("update");
+
+            let mut resyncing = self.resyncing.lock().unwrap();
+            if is_update && resyncing.contains(&inst_id) {
+                continue;
+            }
+
+            let mut books = self.books.write().unwrap();
+            let result = if is_update {
+                match books.get_mut(&inst_id) {
+                    Some(book) => book.apply_update(&d),
+                    None => Err(BookFault::NoSnapshot),
+                }
+            } else {
+                books.entry(inst_id.clone()).or_insert_with(OrderBook::new).apply_snapshot(&d)
+            };
+
+            match result {
+                Ok(()) => {
+                    if !is_update && resyncing.remove(&inst_id) {
+                        info!("📚 [深度] {} 已重新同步", inst_id);
+                    }
+                }
+                Err(fault) => {
+                    error!("❌ [深度] {} {}: {}，丢弃本地深度并重新订阅", arg.channel, inst_id, fault);
+                    books.remove(&inst_id);
+                    resyncing.insert(inst_id);
+                    self.resync_queue.lock().unwrap().push(arg.clone());
+                }
+            }
+        }
+    }
+
+    /// 取出需要重新订阅的频道 (由持有连接的一方执行 unsubscribe + subscribe)
+    pub fn take_resync(&self) -> Vec<WsArg> {
+        let mut queue = self.resync_queue.lock().unwrap();
+        let mut args: Vec<WsArg> = queue.drain(..).collect();
+        args.sort();
+        args.dedup();
+        args
+    }
+
+    /// 断线后全部作废
+    pub fn clear(&self) {
+        self.books.write().unwrap().clear();
+        self.resyncing.lock().unwrap().clear();
+        self.resync_queue.lock().unwrap().clear();
+    }
+
+    /// 在读锁内访问某个品种的深度 (未同步的品种返回 None)
+    pub fn with_book<R>(&self, inst_id: &str, f: impl FnOnce(&OrderBook) -> R) -> Option<R> {
+        self.books.read().unwrap().get(inst_id).map(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book_data(json: &str) -> BookData {
+        serde_json::from_str(json).unwrap()
+    }
+
+    // OKX 文档示例: 校验串为 "3366.1:7:3366.8:9:3366:6:3368:8"
+    #[test]
+    fn checksum_matches_okx_example() {
+        let mut book = OrderBook::new();
+        let snapshot = book_data(r#"{
+            "bids": [["3366.1","7","0","3"],["3366","6","3","4"]],
+            "asks": [["3366.8","9","10","3"],["3368","8","3","4"]],
+            "ts": "1597026383085", "checksum": -1881014294, "prevSeqId": -1, "seqId": 100
+        }"#);
+        assert!(book.apply_snapshot(&snapshot).is_ok());
+        assert_eq!(book.checksum(), -1881014294);
+    }
+
+    // 档位数不等时缺的一侧直接跳过: "3366.1:7:3366.8:9:3366:6"
+    #[test]
+    fn checksum_with_uneven_sides() {
+        let mut book = OrderBook::new();
+        let snapshot = book_data(r#"{
+            "bids": [["3366.1","7","0","3"],["3366","6","3","4"]],
+            "asks": [["3366.8","9","10","3"]],
+            "ts": "1597026383085", "checksum": 1164732920, "seqId": 100
+        }"#);
+        assert!(book.apply_snapshot(&snapshot).is_ok());
+    }
+
+    #[test]
+    fn checksum_mismatch_is_reported() {
+        let mut book = OrderBook::new();
+        let snapshot = book_data(r#"{
+            "bids": [["3366.1","7","0","3"]], "asks": [["3366.8","9","10","3"]],
+            "ts": "1597026383085", "checksum": 123, "seqId": 100
+        }"#);
+        assert!(matches!(book.apply_snapshot(&snapshot), Err(BookFault::ChecksumMismatch { expected: 123, .. })));
+    }
+
+    #[test]
+    fn update_in_sequence_is_applied() {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&book_data(r#"{
+            "bids": [["3366.1","7","0","3"],["3366","6","3","4"]],
+            "asks": [["3366.8","9","10","3"],["3368","8","3","4"]],
+            "ts": "1597026383085", "seqId": 100
+        }"#)).unwrap();
+        // 删除 3366.1 买档后校验串变为 "3366:6:3366.8:9:3368:8"
+        let update = book_data(r#"{
+            "bids": [["3366.1","0","0","0"]], "asks": [],
+            "ts": "1597026383090", "prevSeqId": 100, "seqId": 101
+        }"#);
+        assert!(book.apply_update(&update).is_ok());
+        assert_eq!(book.checksum(), crc32fast::hash(b"3366:6:3366.8:9:3368:8") as i32);
+        assert_eq!(book.seq_id, Some(101));
+    }
+
+    #[test]
+    fn update_with_sequence_gap_is_rejected() {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&book_data(r#"{
+            "bids": [["3366.1","7","0","3"]], "asks": [["3366.8","9","10","3"]],
+            "ts": "1597026383085", "seqId": 100
+        }"#)).unwrap();
+        let update = book_data(r#"{
+            "bids": [["3366","6","0","1"]], "asks": [],
+            "ts": "1597026383090", "prevSeqId": 102, "seqId": 103
+        }"#);
+        assert!(matches!(book.apply_update(&update), Err(BookFault::SequenceGap { expected: 100, got: 102 })));
+        // 出现缺口的增量不应写入本地深度
+        assert!(!book.bids.contains_key(&Px(3366.0)));
+    }
+
+    // 卖盘 100 x 1 / 101 x 1 / 102 x 2，买盘 99 x 1 / 98 x 2
+    fn ladder() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&book_data(r#"{
+            "bids": [["99","1","0","1"],["98","2","0","1"]],
+            "asks": [["100","1","0","1"],["101","1","0","1"],["102","2","0","1"]],
+            "ts": "1597026383085", "seqId": 100
+        }"#)).unwrap();
+        book
+    }
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn sweep_walks_levels() {
+        let book = ladder();
+
+        // 吃满第一档，再吃第二档的一部分
+        let e = book.sweep(Side::Buy, 150.0).unwrap();
+        assert!(e.complete);
+        assert_eq!((e.best_px, e.worst_px), (100.0, 101.0));
+        assert!(approx(e.filled_notional, 150.0));
+        assert!(approx(e.filled_qty, 1.0 + 50.0 / 101.0));
+        assert!(approx(e.avg_px, 150.0 / (1.0 + 50.0 / 101.0)));
+
+        // 深度不足
+        let e = book.sweep(Side::Buy, 1000.0).unwrap();
+        assert!(!e.complete);
+        assert!(approx(e.filled_notional, 405.0));
+        assert!(approx(e.filled_qty, 4.0));
+        assert_eq!(e.worst_px, 102.0);
+
+        // 卖出吃买盘 (从高到低)
+        let e = book.sweep(Side::Sell, 99.0).unwrap();
+        assert!(e.complete);
+        assert_eq!((e.best_px, e.worst_px, e.avg_px), (99.0, 99.0, 99.0));
+        assert!(approx(e.slippage(), 0.0));
+    }
+
+    #[test]
+    fn sweep_and_max_notional_on_one_sided_book() {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(&book_data(r#"{ "bids": [], "asks": [["100","1","0","1"]], "ts": "0", "seqId": 1 }"#)).unwrap();
+        assert!(book.sweep(Side::Sell, 10.0).is_none());
+        assert_eq!(book.max_notional(Side::Sell, 0.01), 0.0);
+        assert!(book.sweep(Side::Buy, 10.0).is_some());
+        assert!(approx(book.max_notional(Side::Buy, 0.01), 100.0));
+    }
+
+    #[test]
+    fn max_notional_with_zero_budget_takes_only_the_best_level() {
+        let book = ladder();
+        assert!(approx(book.max_notional(Side::Buy, 0.0), 100.0));
+        assert!(approx(book.max_notional(Side::Sell, 0.0), 99.0));
+    }
+
+    #[test]
+    fn max_notional_takes_whole_level_when_average_hits_the_limit() {
+        // 吃下前两档后均价恰好 100.5
+        let book = ladder();
+        assert!(approx(book.max_notional(Side::Buy, 0.005), 201.0));
+    }
+
+    #[test]
+    fn max_notional_partial_level_closed_form() {
+        let book = ladder();
+
+        // 买入上限 100.25: 第二档只能吃 x = (100.25·1 - 100) / (1 - 100.25 / 101)
+        let max = book.max_notional(Side::Buy, 0.0025);
+        assert!(approx(max, 100.0 + 0.25 / (1.0 - 100.25 / 101.0)));
+        assert!(approx(book.sweep(Side::Buy, max).unwrap().avg_px, 100.25));
+
+        // 卖出下限 98.505: 第二档 (98) 只能吃一部分
+        let max = book.max_notional(Side::Sell, 0.005);
+        let limit = 99.0 * (1.0 - 0.005);
+        assert!(approx(max, 99.0 + (limit - 99.0) / (1.0 - limit / 98.0)));
+        assert!(approx(book.sweep(Side::Sell, max).unwrap().avg_px, limit));
+    }
+
+    #[test]
+    fn max_notional_is_clamped_to_the_book() {
+        let book = ladder();
+        // 预算足够大时吃完整个盘口
+        assert!(approx(book.max_notional(Side::Buy, 1.0), 405.0));
+        assert!(approx(book.max_notional(Side::Sell, 0.9), 295.0));
+        // 预算为负 (上限比最优价还好) 时一点也吃不下
+        assert_eq!(book.max_notional(Side::Buy, -0.01), 0.0);
+        assert_eq!(book.max_notional(Side::Sell, -0.01), 0.0);
+    }
+}
@@ -0,0 +1,207 @@
+// src/okx/order_id.rs
+
+use crate::okx::error::{OkxError, OkxResult};
+use crate::utils::time;
+use std::sync::{Mutex, OnceLock};
+
+// ==========================================
+// 🆔 clOrdId 生成器
+// 格式: {前缀 1-16}{策略 2}{信号 2}{毫秒时间戳 base36 9}{序号 base36 3}
+// 尾部 16 位定长，从后往前即可解码出策略 / 信号 / 时间；前缀区分实例
+// 时间部分取自墙钟毫秒，重启后天然不会与上次运行重复
+// ==========================================
+
+const MAX_LEN: usize = 32; // OKX 限制: 1-32 位字母数字
+const CODE_LEN: usize = 2;
+const TS_LEN: usize = 9; // 36^9 毫秒 ≈ 3 千年
+const SEQ_LEN: usize = 3; // 同一毫秒内最多 46656 笔
+const TAIL_LEN: usize = CODE_LEN * 2 + TS_LEN + SEQ_LEN;
+pub const MAX_PREFIX_LEN: usize = MAX_LEN - TAIL_LEN;
+const SEQ_MAX: u64 = 36u64.pow(SEQ_LEN as u32);
+
+pub const DEFAULT_PREFIX: &str = "snip";
+
+/// 未标明来源的订单使用的策略 / 信号代码
+pub const UNKNOWN_CODE: &str = "xx";
+
+static PREFIX: OnceLock<String> = OnceLock::new();
+// 本次运行的启动时间 (毫秒)，更早的 clOrdId 属于上一次运行
+static STARTED_MS: OnceLock<u64> = OnceLock::new();
+// (上次使用的毫秒, 该毫秒内的序号)
+static LAST: Mutex<(u64, u64)> = Mutex::new((0, 0));
+
+/// 📝 启动时设置实例前缀 (只生效一次)
+pub fn init(prefix: &str) -> OkxResult<()> {
+    validate_prefix(prefix)?;
+    PREFIX.set(prefix.to_string())
+        .map_err(|_| OkxError::Config("clOrdId 前缀已初始化".to_string()))?;
+    let started = time::local_timestamp_ms().max(0) as u64;
+    let _ = STARTED_MS.set(started);
+    seed(started);
+    Ok(())
+}
+
+/// 以启动时间为下限：启动后时钟回拨也不会生成早于 STARTED_MS 的 clOrdId (否则 is_own 会把它当成上一次运行的订单)
+fn seed(started: u64) {
+    let mut last = LAST.lock().unwrap();
+    if last.0 < started {
+        *last = (started, 0);
+    }
+}
+
+/// 当前实例前缀 (未初始化时为默认前缀)
+pub fn prefix() -> &'static str {
+    PREFIX.get().map(String::as_str).unwrap_or(DEFAULT_PREFIX)
+}
+
+/// 🔐 是否为本实例本次运行中由指定策略生成的订单
+/// 手动下单、其他程序 / 实例、上一次运行遗留的订单一律返回 false
+pub fn is_own(cl_ord_id: &str, strategy: &str) -> bool {
+    let started = STARTED_MS.get().copied().unwrap_or(0);
+    decode(cl_ord_id).is_some_and(|info| info.prefix == prefix() && info.strategy == strategy && info.ts_ms >= started)
+}
+
+pub fn validate_prefix(prefix: &str) -> OkxResult<()> {
+    if prefix.is_empty() || prefix.len() > MAX_PREFIX_LEN || !is_alphanumeric(prefix) {
+        return Err(OkxError::Config(format!("clOrdId 前缀必须是 1-{} 位字母数字: {:?}", MAX_PREFIX_LEN, prefix)));
+    }
+    Ok(())
+}
+
+/// 🆕 生成 clOrdId；strategy / signal 为 2 位字母数字代码
+pub fn next(strategy: &str, signal: &str) -> OkxResult<String> {
+    for code in [strategy, signal] {
+        if code.len() != CODE_LEN || !is_alphanumeric(code) {
+            return Err(OkxError::InvalidOrder(format!("clOrdId 策略 / 信号代码必须是 {} 位字母数字: {:?}", CODE_LEN, code)));
+        }
+    }
+
+    let (ms, seq) = {
+        let mut last = LAST.lock().unwrap();
+        *last = advance(*last, time::local_timestamp_ms().max(0) as u64);
+        *last
+    };
+
+    Ok(format!("{}{}{}{}{}", prefix(), strategy, signal, base36(ms, TS_LEN), base36(seq, SEQ_LEN)))
+}
+
+/// 时钟回拨或同一毫秒内: 沿用上次的毫秒并递增序号；序号用完则借用下一毫秒
+fn advance(last: (u64, u64), now: u64) -> (u64, u64) {
+    if now > last.0 {
+        (now, 0)
+    } else if last.1 + 1 < SEQ_MAX {
+        (last.0, last.1 + 1)
+    } else {
+        (last.0 + 1, 0)
+    }
+}
+
+/// 🔍 clOrdId 解码结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClOrdIdInfo {
+    pub prefix: String,
+    pub strategy: String,
+    pub signal: String,
+    pub ts_ms: u64,
+    pub seq: u64,
+}
+
+/// 解码 clOrdId (非本格式的订单返回 None，如手动下单)
+pub fn decode(cl_ord_id: &str) -> Option<ClOrdIdInfo> {
+    if cl_ord_id.len() <= TAIL_LEN || cl_ord_id.len() > MAX_LEN || !is_alphanumeric(cl_ord_id) {
+        return None;
+    }
+    let (prefix, tail) = cl_ord_id.split_at(cl_ord_id.len() - TAIL_LEN);
+    let (strategy, rest) = tail.split_at(CODE_LEN);
+    let (signal, rest) = rest.split_at(CODE_LEN);
+    let (ts, seq) = rest.split_at(TS_LEN);
+    Some(ClOrdIdInfo {
+        prefix: prefix.to_string(),
+        strategy: strategy.to_string(),
+        signal: signal.to_string(),
+        ts_ms: u64::from_str_radix(ts, 36).ok()?,
+        seq: u64::from_str_radix(seq, 36).ok()?,
+    })
+}
+
+fn is_alphanumeric(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// 定长小写 base36 (左侧补 0)
+fn base36(mut n: u64, width: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut buf = vec![b'0'; width];
+    for slot in buf.iter_mut().rev() {
+        *slot = DIGITS[(n % 36) as usize];
+        n /= 36;
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_then_decode_round_trip() {
+        let before = time::local_timestamp_ms() as u64;
+        let id = next("fc", "en").unwrap();
+        assert_eq!(id.len(), prefix().len() + TAIL_LEN);
+
+        let info = decode(&id).unwrap();
+        assert_eq!(info.prefix, prefix());
+        assert_eq!(info.strategy, "fc");
+        assert_eq!(info.signal, "en");
+        assert!(info.ts_ms >= before);
+        assert!(info.seq < SEQ_MAX);
+    }
+
+    #[test]
+    fn ids_are_unique_and_ordered() {
+        let ids: Vec<String> = (0..2000).map(|_| next("fc", "tp").unwrap()).collect();
+        let keys: Vec<(u64, u64)> = ids.iter().map(|id| decode(id).map(|i| (i.ts_ms, i.seq)).unwrap()).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn clock_behind_start_never_goes_below_start() {
+        let started = 1_700_000_000_000;
+        let mut last = (started, 0);
+        for _ in 0..SEQ_MAX + 5 {
+            last = advance(last, started - 60_000);
+            assert!(last.0 >= started);
+        }
+        assert_eq!(last, (started + 1, 5)); // 序号用完后借用下一毫秒
+
+        // init 的种子: 启动时间之后生成的 clOrdId 一律不早于它
+        let future = time::local_timestamp_ms() as u64 + 60_000;
+        seed(future);
+        let info = decode(&next("fc", "en").unwrap()).unwrap();
+        assert!(info.ts_ms >= future);
+    }
+
+    #[test]
+    fn invalid_codes_are_rejected() {
+        assert!(next("f", "en").is_err());
+        assert!(next("fc", "e-").is_err());
+    }
+
+    #[test]
+    fn foreign_ids_do_not_decode() {
+        assert_eq!(decode(""), None);
+        assert_eq!(decode("manual1"), None); // 不足定长尾部
+        assert_eq!(decode("snipfcen0000000000-0"), None); // 非字母数字
+        assert_eq!(decode(&"a".repeat(MAX_LEN + 1)), None);
+    }
+
+    #[test]
+    fn ownership_requires_prefix_and_strategy() {
+        let id = next("fc", "sl").unwrap();
+        assert!(is_own(&id, "fc"));
+        assert!(!is_own(&id, "xx"));
+        let other_instance = format!("other{}", &id[prefix().len()..]);
+        assert!(!is_own(&other_instance, "fc"));
+        assert!(!is_own("manual-order", "fc"));
+    }
+}
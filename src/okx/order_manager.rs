@@ -0,0 +1,568 @@
+// src/okx/order_manager.rs
+
+use crate::okx::correlation::OpResult;
+use crate::okx::error::{OkxError, RejectReason};
+use crate::okx::order_id;
+use crate::okx::protocol::{OpPacket, Side};
+use crate::okx::trade_data::Order;
+use log::{debug, info, warn, error};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// 终态订单保留一段时间，便于迟到的推送 / 查询
+const TERMINAL_RETENTION: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderState {
+    /// 已发出，等待交易所确认
+    PendingNew,
+    Live,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    /// 下单回执 sCode 非 0
+    Rejected { code: String, msg: String },
+    /// 迟迟没有回执 (订单可能仍在交易所，后续推送会继续更新状态)
+    TimedOut,
+}
+
+impl OrderState {
+    /// orders 频道的 state 字段
+    fn from_push(state: &str) -> Option<Self> {
+        match state {
+            "live" => Some(OrderState::Live),
+            "partially_filled" => Some(OrderState::PartiallyFilled),
+            "filled" => Some(OrderState::Filled),
+            "canceled" | "mmp_canceled" => Some(OrderState::Canceled),
+            _ => None,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderState::Filled | OrderState::Canceled | OrderState::Rejected { .. })
+    }
+
+    /// 推送可能乱序到达，状态只允许向前推进
+    fn rank(&self) -> u8 {
+        match self {
+            OrderState::PendingNew | OrderState::TimedOut => 0,
+            OrderState::Live => 1,
+            OrderState::PartiallyFilled => 2,
+            OrderState::Filled | OrderState::Canceled | OrderState::Rejected { .. } => 3,
+        }
+    }
+}
+
+/// 💵 单笔成交 (fee 为负表示扣费，正数为返佣)
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub trade_id: String,
+    pub px: f64,
+    pub sz: f64,
+    pub fee: f64,
+    pub fee_ccy: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedOrder {
+    pub cl_ord_id: String,
+    pub inst_id: String,
+    pub side: Side,
+    pub ord_id: Option<String>,
+    pub state: OrderState,
+    // 累计成交数量 / 成交均价
+    pub filled_sz: f64,
+    pub avg_px: f64,
+    acked: bool,
+    updated_at: Instant,
+}
+
+/// 📣 订单状态变化 (附带本次新增的成交)，交给策略处理
+#[derive(Debug, Clone)]
+pub struct OrderUpdate {
+    pub cl_ord_id: String,
+    pub inst_id: String,
+    pub side: Side,
+    pub state: OrderState,
+    pub fill: Option<Fill>,
+}
+
+/// 📒 [订单管理器] 按 clOrdId 跟踪每一笔订单
+/// 数据来源: 交易请求回执 (由连接上的 RequestTracker 配对后的 OpResult) + orders 频道推送
+pub struct OrderManager {
+    orders: HashMap<String, TrackedOrder>,
+}
+
+impl Default for OrderManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderManager {
+    pub fn new() -> Self {
+        OrderManager { orders: HashMap::new() }
+    }
+
+    /// 📝 登记即将发出的下单请求 (撤单 / 改单不产生新订单)
+    pub fn track(&mut self, packet: &OpPacket) {
+        if !is_place_op(packet.op) {
+            return;
+        }
+        let now = Instant::now();
+        for target in &packet.targets {
+            let Some(side) = target.side else { continue };
+            self.orders.insert(target.cl_ord_id.clone(), TrackedOrder {
+                cl_ord_id: target.cl_ord_id.clone(),
+                inst_id: target.inst_id.clone(),
+                side,
+                ord_id: None,
+                state: OrderState::PendingNew,
+                filled_sz: 0.0,
+                avg_px: 0.0,
+                acked: false,
+                updated_at: now,
+            });
+        }
+    }
+
+    /// 📨 处理交易请求回执 (order / batch-orders / cancel / amend)
+    /// 逐笔检查结果；返回因此变化的订单 (下单被拒 / 超时 / 撤单查无此单)
+    pub fn on_result(&mut self, result: &OpResult) -> Vec<OrderUpdate> {
+        debug!("📨 [{}] {} 回执 {} 笔 ({}ms)", result.op, result.req_id, result.items.len(), result.latency.as_millis());
+        let mut updates = Vec::new();
+        for item in &result.items {
+            match &item.result {
+                Ok(()) => self.on_item_accepted(result.op, &item.cl_ord_id, &item.ord_id),
+                Err(OkxError::Timeout(_)) => updates.extend(self.on_item_timeout(result.op, &item.cl_ord_id)),
+                Err(e) => {
+                    let (code, msg) = e.code_and_msg();
+                    updates.extend(self.on_item_failed(result.op, &item.cl_ord_id, code, msg));
+                }
+            }
+        }
+        updates
+    }
+
+    fn on_item_accepted(&mut self, op: &str, cl_ord_id: &str, ord_id: &str) {
+        match op {
+            "cancel-order" | "batch-cancel-orders" => info!("✂️ [撤单] {} 已受理", cl_ord_id),
+            "amend-order" | "batch-amend-orders" => info!("✏️ [改单] {} 已受理", cl_ord_id),
+            _ => {}
+        }
+        if let Some(order) = self.orders.get_mut(cl_ord_id) {
+            order.acked = true;
+            if !ord_id.is_empty() {
+                order.ord_id = Some(ord_id.to_string());
+            }
+            if order.state == OrderState::TimedOut {
+                order.state = OrderState::PendingNew;
+            }
+        }
+    }
+
+    /// 单笔超时：下单无回执时订单标记为超时 (可能仍在交易所，需要补发撤单)；
+    /// 超时订单的撤单也超时时再次返回，由策略重发撤单；其余撤单 / 改单超时只告警
+    fn on_item_timeout(&mut self, op: &str, cl_ord_id: &str) -> Option<OrderUpdate> {
+        if matches!(op, "cancel-order" | "batch-cancel-orders") {
+            let Some(order) = self.orders.get_mut(cl_ord_id).filter(|o| o.state == OrderState::TimedOut) else {
+                warn!("⏰ [{}] {} 未收到回执，以推送为准", op, cl_ord_id);
+                return None;
+            };
+            warn!("⏰ [撤单] {} {} 未收到回执，重发撤单", order.inst_id, cl_ord_id);
+            return Some(Self::time_out(order));
+        }
+        if !is_place_op(op) {
+            warn!("⏰ [{}] {} 未收到回执，以推送为准", op, cl_ord_id);
+            return None;
+        }
+        let order = self.orders.get_mut(cl_ord_id)?;
+        // 推送先于回执到达时订单已确认
+        if order.acked || order.state != OrderState::PendingNew {
+            return None;
+        }
+        error!("⏰ [订单] {} {} 未收到回执", order.inst_id, cl_ord_id);
+        Some(Self::time_out(order))
+    }
+
+    /// 🔌 私有连接断开：仍未确认的订单一律标记超时 (在途请求的回执不会再到达)；
+    /// 此前已超时的订单撤单结果同样未知，一并返回，重连后重发撤单
+    pub fn on_connection_lost(&mut self) -> Vec<OrderUpdate> {
+        self.orders.values_mut()
+            .filter(|o| o.state == OrderState::TimedOut || (!o.acked && o.state == OrderState::PendingNew))
+            .map(|order| {
+                if order.state == OrderState::TimedOut {
+                    warn!("⏰ [订单] {} {} 撤单结果未知连接已断开，重连后重发撤单", order.inst_id, order.cl_ord_id);
+                } else {
+                    error!("⏰ [订单] {} {} 回执未到连接已断开", order.inst_id, order.cl_ord_id);
+                }
+                Self::time_out(order)
+            })
+            .collect()
+    }
+
+    fn time_out(order: &mut TrackedOrder) -> OrderUpdate {
+        order.state = OrderState::TimedOut;
+        order.updated_at = Instant::now();
+        OrderUpdate {
+            cl_ord_id: order.cl_ord_id.clone(),
+            inst_id: order.inst_id.clone(),
+            side: order.side,
+            state: OrderState::TimedOut,
+            fill: None,
+        }
+    }
+
+    /// 单笔失败：下单被拒改变订单状态；撤单 / 改单失败不影响订单本身 (以推送为准)
+    fn on_item_failed(&mut self, op: &str, cl_ord_id: &str, code: String, msg: String) -> Option<OrderUpdate> {
+        match op {
+            "cancel-order" | "batch-cancel-orders" => {
+                warn!("⚠️ [撤单] {} 失败: [{}] {}", cl_ord_id, code, msg);
+                // 超时订单撤单返回"订单不存在": 交易所从未收到这笔订单，按已撤销终结
+                let never_placed = matches!(RejectReason::from_code(&code), RejectReason::OrderNotFound)
+                    && self.orders.get(cl_ord_id).is_some_and(|o| o.state == OrderState::TimedOut);
+                if never_placed {
+                    return self.finish_never_placed(cl_ord_id);
+                }
+                None
+            }
+            "amend-order" | "batch-amend-orders" => {
+                warn!("⚠️ [改单] {} 失败: [{}] {}", cl_ord_id, code, msg);
+                None
+            }
+            _ => self.reject(cl_ord_id, code, msg),
+        }
+    }
+
+    /// 📑 处理 orders 频道推送，状态 / 成交有变化时返回更新
+    pub fn on_push(&mut self, push: Order) -> Option<OrderUpdate> {
+        let Some(new_state) = OrderState::from_push(&push.state) else {
+            warn!("⚠️ [订单] {} 未知状态: {}", push.client_oid, push.state);
+            return None;
+        };
+
+        let now = Instant::now();
+        let order = self.orders.entry(push.client_oid.clone()).or_insert_with(|| {
+            // 非本进程发出的订单 (手动下单 / 上次运行遗留 / 其他实例) 也记录下来，避免丢失成交
+            match order_id::decode(&push.client_oid) {
+                Some(info) => warn!(
+                    "⚠️ [订单] 收到未登记订单推送: {} ({}) | 前缀 {} 策略 {} 信号 {} 下单于 {}",
+                    push.client_oid, push.inst_id, info.prefix, info.strategy, info.signal, info.ts_ms
+                ),
+                None => warn!("⚠️ [订单] 收到未登记订单推送: {} ({}) | 非本程序格式", push.client_oid, push.inst_id),
+            }
+            TrackedOrder {
+                cl_ord_id: push.client_oid.clone(),
+                inst_id: push.inst_id.clone(),
+                side: push.side,
+                ord_id: None,
+                state: OrderState::PendingNew,
+                filled_sz: 0.0,
+                avg_px: 0.0,
+                acked: true,
+                updated_at: now,
+            }
+        });
+        order.acked = true;
+        order.ord_id = Some(push.ord_id.clone());
+
+        // 累计成交量只增不减，据此过滤重复 / 迟到的成交推送
+        let acc_fill_sz = parse_num(&push.acc_fill_sz);
+        let fill = if acc_fill_sz > order.filled_sz && !push.trade_id.is_empty() {
+            order.filled_sz = acc_fill_sz;
+            order.avg_px = parse_num(&push.avg_px);
+            Some(Fill {
+                trade_id: push.trade_id,
+                px: parse_num(&push.fill_px),
+                sz: parse_num(&push.fill_sz),
+                fee: parse_num(&push.fill_fee),
+                fee_ccy: push.fill_fee_ccy,
+            })
+        } else {
+            None
+        };
+
+        let advanced = new_state.rank() > order.state.rank() && !order.state.is_terminal();
+        if !advanced && fill.is_none() {
+            return None;
+        }
+        if advanced {
+            info!("📑 [订单] {} {} {:?} -> {:?} | 已成交 {} @ {}", order.inst_id, order.cl_ord_id, order.state, new_state, order.filled_sz, order.avg_px);
+            order.state = new_state;
+        }
+        order.updated_at = now;
+
+        Some(OrderUpdate {
+            cl_ord_id: order.cl_ord_id.clone(),
+            inst_id: order.inst_id.clone(),
+            side: order.side,
+            state: order.state.clone(),
+            fill,
+        })
+    }
+
+    /// ⏰ 定时调用：清理过期的终态订单
+    pub fn prune(&mut self) {
+        self.prune_at(Instant::now());
+    }
+
+    fn prune_at(&mut self, now: Instant) {
+        self.orders.retain(|_, o| !(o.state.is_terminal() && now - o.updated_at > TERMINAL_RETENTION));
+    }
+
+    fn finish_never_placed(&mut self, cl_ord_id: &str) -> Option<OrderUpdate> {
+        let order = self.orders.get_mut(cl_ord_id)?;
+        warn!("🕳️ [订单] {} {} 交易所查无此单，视为未下单", order.inst_id, cl_ord_id);
+        order.acked = true;
+        order.state = OrderState::Canceled;
+        order.updated_at = Instant::now();
+        Some(OrderUpdate {
+            cl_ord_id: order.cl_ord_id.clone(),
+            inst_id: order.inst_id.clone(),
+            side: order.side,
+            state: OrderState::Canceled,
+            fill: None,
+        })
+    }
+
+    fn reject(&mut self, cl_ord_id: &str, code: String, msg: String) -> Option<OrderUpdate> {
+        let order = self.orders.get_mut(cl_ord_id)?;
+        if order.state.is_terminal() {
+            return None;
+        }
+        error!("❌ [订单] {} {} {}", order.inst_id, cl_ord_id, OkxError::from_item(&code, &msg));
+        order.acked = true;
+        order.state = OrderState::Rejected { code, msg };
+        order.updated_at = Instant::now();
+        Some(OrderUpdate {
+            cl_ord_id: order.cl_ord_id.clone(),
+            inst_id: order.inst_id.clone(),
+            side: order.side,
+            state: order.state.clone(),
+            fill: None,
+        })
+    }
+}
+
+fn is_place_op(op: &str) -> bool {
+    matches!(op, "order" | "batch-orders")
+}
+
+fn parse_num(s: &str) -> f64 {
+    s.parse().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::okx::correlation::ItemResult;
+    use crate::okx::error::OkxResult;
+    use crate::okx::protocol::OrderTarget;
+
+    fn packet(op: &'static str, cl_ord_id: &str, side: Side) -> OpPacket {
+        OpPacket {
+            req_id: format!("req-{}", cl_ord_id),
+            op,
+            targets: vec![OrderTarget { cl_ord_id: cl_ord_id.to_string(), inst_id: "BTC-USDT".to_string(), side: Some(side) }],
+            payload: String::new(),
+        }
+    }
+
+    fn result(op: &'static str, cl_ord_id: &str, ord_id: &str, result: OkxResult<()>) -> OpResult {
+        OpResult {
+            req_id: format!("req-{}", cl_ord_id),
+            op,
+            latency: Duration::ZERO,
+            items: vec![ItemResult { cl_ord_id: cl_ord_id.to_string(), ord_id: ord_id.to_string(), result }],
+        }
+    }
+
+    fn push(cl_ord_id: &str, state: &str, acc_fill_sz: &str, trade_id: &str, fill_sz: &str) -> Order {
+        serde_json::from_value(serde_json::json!({
+            "instId": "BTC-USDT", "ordId": "1", "clOrdId": cl_ord_id, "px": "", "sz": "1", "side": "buy",
+            "state": state, "cTime": "0", "accFillSz": acc_fill_sz, "avgPx": "100",
+            "tradeId": trade_id, "fillPx": "100", "fillSz": fill_sz, "fillFee": "-0.001", "fillFeeCcy": "BTC",
+        })).unwrap()
+    }
+
+    fn timeout() -> OkxResult<()> {
+        Err(OkxError::Timeout("测试".to_string()))
+    }
+
+    fn states(updates: &[OrderUpdate]) -> Vec<(&str, OrderState)> {
+        updates.iter().map(|u| (u.cl_ord_id.as_str(), u.state.clone())).collect()
+    }
+
+    #[test]
+    fn timed_out_order_is_canceled_again_after_cancel_timeout_and_reconnect() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+
+        // 下单无回执 -> 超时，补发撤单
+        let updates = mgr.on_result(&result("order", "a", "", timeout()));
+        assert_eq!(states(&updates), vec![("a", OrderState::TimedOut)]);
+
+        // 撤单同样无回执 -> 结果未知，再次要求撤单
+        let updates = mgr.on_result(&result("cancel-order", "a", "", timeout()));
+        assert_eq!(states(&updates), vec![("a", OrderState::TimedOut)]);
+
+        // 私有连接断开 -> 重连后重发撤单
+        assert_eq!(states(&mgr.on_connection_lost()), vec![("a", OrderState::TimedOut)]);
+        assert_eq!(states(&mgr.on_connection_lost()), vec![("a", OrderState::TimedOut)]);
+
+        // 交易所查无此单 -> 终结，之后不再重发
+        let not_found = Err(OkxError::from_item("51603", "Order does not exist"));
+        let updates = mgr.on_result(&result("cancel-order", "a", "", not_found));
+        assert_eq!(states(&updates), vec![("a", OrderState::Canceled)]);
+        assert!(mgr.on_result(&result("cancel-order", "a", "", timeout())).is_empty());
+        assert!(mgr.on_connection_lost().is_empty());
+    }
+
+    #[test]
+    fn cancel_timeout_of_a_live_order_is_left_to_pushes() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        assert!(mgr.on_result(&result("order", "a", "1", Ok(()))).is_empty());
+        assert!(mgr.on_result(&result("cancel-order", "a", "", timeout())).is_empty());
+        assert!(mgr.on_connection_lost().is_empty());
+    }
+
+    #[test]
+    fn track_registers_place_ops_only() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("cancel-order", "c", Side::Buy));
+        mgr.track(&packet("amend-order", "m", Side::Buy));
+        assert!(mgr.orders.is_empty());
+
+        mgr.track(&packet("order", "a", Side::Sell));
+        let order = &mgr.orders["a"];
+        assert_eq!((order.side, &order.state, order.acked), (Side::Sell, &OrderState::PendingNew, false));
+    }
+
+    #[test]
+    fn ack_records_ord_id_and_late_ack_revives_timed_out_order() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        assert!(mgr.on_result(&result("order", "a", "123", Ok(()))).is_empty());
+        assert_eq!(mgr.orders["a"].ord_id.as_deref(), Some("123"));
+        assert!(mgr.orders["a"].acked);
+
+        // 断线判定超时后，回执才迟到
+        mgr.track(&packet("order", "b", Side::Buy));
+        assert_eq!(states(&mgr.on_connection_lost()), vec![("b", OrderState::TimedOut)]);
+        assert!(mgr.on_result(&result("order", "b", "456", Ok(()))).is_empty());
+        assert_eq!(mgr.orders["b"].state, OrderState::PendingNew);
+    }
+
+    #[test]
+    fn rejected_item_is_terminal() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        let updates = mgr.on_result(&result("order", "a", "", Err(OkxError::from_item("51008", "Insufficient balance"))));
+        assert!(matches!(updates.as_slice(), [u] if matches!(&u.state, OrderState::Rejected { code, .. } if code == "51008")));
+        // 重复的拒绝 / 超时不再产生更新
+        assert!(mgr.on_result(&result("order", "a", "", Err(OkxError::from_item("51008", "Insufficient balance")))).is_empty());
+        assert!(mgr.on_result(&result("order", "a", "", timeout())).is_empty());
+    }
+
+    #[test]
+    fn timeout_after_push_is_ignored() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        mgr.on_push(push("a", "live", "0", "", ""));
+        assert!(mgr.on_result(&result("order", "a", "", timeout())).is_empty());
+        assert_eq!(mgr.orders["a"].state, OrderState::Live);
+    }
+
+    #[test]
+    fn connection_lost_times_out_unacked_orders_only() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        mgr.track(&packet("order", "b", Side::Buy));
+        mgr.on_result(&result("order", "b", "2", Ok(())));
+        assert_eq!(states(&mgr.on_connection_lost()), vec![("a", OrderState::TimedOut)]);
+    }
+
+    #[test]
+    fn push_before_ack_is_kept() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        let update = mgr.on_push(push("a", "filled", "1", "t1", "1")).unwrap();
+        assert_eq!(update.state, OrderState::Filled);
+        assert_eq!(update.fill.as_ref().map(|f| f.sz), Some(1.0));
+
+        // 回执迟到: 不回退状态，也不再产生更新
+        assert!(mgr.on_result(&result("order", "a", "1", Ok(()))).is_empty());
+        assert!(mgr.on_result(&result("order", "a", "", timeout())).is_empty());
+        assert_eq!(mgr.orders["a"].state, OrderState::Filled);
+    }
+
+    #[test]
+    fn repeated_fill_pushes_are_deduped_by_acc_fill_sz() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+
+        let first = mgr.on_push(push("a", "partially_filled", "0.4", "t1", "0.4")).unwrap();
+        assert_eq!(first.fill.map(|f| f.trade_id), Some("t1".to_string()));
+        // 同一笔成交重复推送
+        assert!(mgr.on_push(push("a", "partially_filled", "0.4", "t1", "0.4")).is_none());
+
+        let second = mgr.on_push(push("a", "filled", "1", "t2", "0.6")).unwrap();
+        assert_eq!((second.state, second.fill.map(|f| f.sz)), (OrderState::Filled, Some(0.6)));
+        // 迟到的旧推送既不回退状态也不重复记成交
+        assert!(mgr.on_push(push("a", "partially_filled", "0.4", "t1", "0.4")).is_none());
+        assert_eq!(mgr.orders["a"].filled_sz, 1.0);
+    }
+
+    #[test]
+    fn push_without_new_fill_only_reports_state_changes() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "a", Side::Buy));
+        assert_eq!(mgr.on_push(push("a", "live", "0", "", "")).map(|u| u.state), Some(OrderState::Live));
+        assert!(mgr.on_push(push("a", "live", "0", "", "")).is_none());
+        assert!(mgr.on_push(push("a", "unknown", "0", "", "")).is_none());
+    }
+
+    #[test]
+    fn untracked_push_is_recorded() {
+        let mut mgr = OrderManager::new();
+        let update = mgr.on_push(push("manual1", "live", "0", "", "")).unwrap();
+        assert_eq!((update.cl_ord_id.as_str(), update.side), ("manual1", Side::Buy));
+        assert!(mgr.orders["manual1"].acked);
+    }
+
+    #[test]
+    fn cancel_not_found_finishes_only_timed_out_orders() {
+        let mut mgr = OrderManager::new();
+        let not_found = || Err(OkxError::from_item("51603", "Order does not exist"));
+
+        // 已确认的订单撤单查无此单：以推送为准
+        mgr.track(&packet("order", "a", Side::Buy));
+        mgr.on_result(&result("order", "a", "1", Ok(())));
+        assert!(mgr.on_result(&result("cancel-order", "a", "", not_found())).is_empty());
+        assert_eq!(mgr.orders["a"].state, OrderState::PendingNew);
+
+        // 超时订单撤单查无此单：交易所从未收到，按已撤销终结
+        mgr.track(&packet("order", "b", Side::Sell));
+        mgr.on_result(&result("order", "b", "", timeout()));
+        let updates = mgr.on_result(&result("cancel-order", "b", "", not_found()));
+        assert_eq!(states(&updates), vec![("b", OrderState::Canceled)]);
+        assert!(updates[0].fill.is_none());
+    }
+
+    #[test]
+    fn prune_drops_only_expired_terminal_orders() {
+        let mut mgr = OrderManager::new();
+        mgr.track(&packet("order", "live", Side::Buy));
+        mgr.track(&packet("order", "done", Side::Buy));
+        mgr.on_push(push("done", "canceled", "0", "", ""));
+
+        mgr.prune();
+        assert_eq!(mgr.orders.len(), 2);
+
+        mgr.prune_at(Instant::now() + TERMINAL_RETENTION + Duration::from_secs(1));
+        assert!(mgr.orders.contains_key("live"));
+        assert!(!mgr.orders.contains_key("done"));
+    }
+}
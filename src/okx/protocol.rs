@@ -1,42 +1,156 @@
+use crate::okx::error::{OkxError, OkxResult};
+use crate::okx::market_data::Instrument;
+use crate::okx::order_id;
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
 
-// ==========================================
-// 🚀 极速 ID 生成器 (Hot Path)
-// ==========================================
-static ORDER_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
-
 // ==========================================
 // 📦 基础枚举与结构
 // ==========================================
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Endpoint {
     Public,
     Private,
+    /// K线 / 策略委托等业务频道
+    Business,
 }
 
 impl Endpoint {
-    pub fn as_url(&self) -> &'static str {
+    /// 官方默认地址 (可被 AppConfig 中的 OKX_WS_*_URL 覆盖)
+    pub fn default_url(&self, env: TradingEnv) -> &'static str {
+        match (env, self) {
+            (TradingEnv::Live, Endpoint::Public) => "wss://ws.okx.com/ws/v5/public",
+            (TradingEnv::Live, Endpoint::Private) => "wss://ws.okx.com/ws/v5/private",
+            (TradingEnv::Live, Endpoint::Business) => "wss://ws.okx.com/ws/v5/business",
+            (TradingEnv::Demo, Endpoint::Public) => "wss://wspap.okx.com:8443/ws/v5/public",
+            (TradingEnv::Demo, Endpoint::Private) => "wss://wspap.okx.com:8443/ws/v5/private",
+            (TradingEnv::Demo, Endpoint::Business) => "wss://wspap.okx.com:8443/ws/v5/business",
+        }
+    }
+
+    /// 私有端点总是需要先 login；业务端点只有订阅了私有频道 (策略委托等) 才需要，
+    /// 只订阅 K 线时不鉴权，没配 API Key 也能拿到 K 线
+    pub fn requires_login(&self, args: &[WsArg]) -> bool {
+        match self {
+            Endpoint::Public => false,
+            Endpoint::Private => true,
+            Endpoint::Business => args.iter().any(|a| !is_public_business_channel(&a.channel)),
+        }
+    }
+}
+
+/// business 端点上无需鉴权的频道 (各类 K 线 / 全量成交)
+fn is_public_business_channel(channel: &str) -> bool {
+    ["candle", "mark-price-candle", "index-candle"].iter().any(|p| channel.starts_with(p)) || channel == "trades-all"
+}
+
+/// 🧪 交易环境：实盘 / 模拟盘 (Demo Trading)
+/// 模拟盘使用独立的 wspap 域名，REST 请求需额外携带 x-simulated-trading: 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingEnv {
+    Live,
+    Demo,
+}
+
+impl TradingEnv {
+    pub fn from_simulation(simulation_mode: bool) -> Self {
+        if simulation_mode { TradingEnv::Demo } else { TradingEnv::Live }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TradingEnv::Live => "实盘 LIVE",
+            TradingEnv::Demo => "模拟盘 DEMO",
+        }
+    }
+
+    /// REST 请求需要附带的额外 Header
+    pub fn rest_headers(&self) -> &'static [(&'static str, &'static str)] {
         match self {
-            Endpoint::Public => "wss://ws.okx.com/ws/v5/public",
-            Endpoint::Private => "wss://ws.okx.com/ws/v5/private",
+            TradingEnv::Live => &[],
+            TradingEnv::Demo => &[("x-simulated-trading", "1")],
         }
     }
 }
 
+/// 🏷️ 产品类型 (instType)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum InstType {
+    Spot,
+    Swap,
+    Futures,
+    Option,
+}
+
+impl InstType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstType::Spot => "SPOT",
+            InstType::Swap => "SWAP",
+            InstType::Futures => "FUTURES",
+            InstType::Option => "OPTION",
+        }
+    }
+
+    /// 现货默认非杠杆 cash 模式，衍生品默认全仓
+    pub fn default_td_mode(&self) -> TdMode {
+        match self {
+            InstType::Spot => TdMode::Cash,
+            InstType::Swap | InstType::Futures | InstType::Option => TdMode::Cross,
+        }
+    }
+}
+
+/// OKX candle 频道支持的周期 (频道名为 "candle" + 周期，仅 business 端点)
+pub const CANDLE_BARS: &[&str] = &[
+    "1s", "1m", "3m", "5m", "15m", "30m", "1H", "2H", "4H",
+    "6H", "12H", "1D", "2D", "3D", "1W", "1M", "3M",
+    "6Hutc", "12Hutc", "1Dutc", "2Dutc", "3Dutc", "1Wutc", "1Mutc", "3Mutc",
+];
+
 #[derive(Debug, Clone, Copy)]
 pub enum ChannelType {
     Tickers,
     Account,
+    Orders,
+    Instruments,
+    /// 400 档深度 (首次全量 + 100ms 增量)
+    Books,
+    /// 5 档深度 (每次全量)
+    Books5,
+    /// 1 档 tick-by-tick (每次全量)
+    BboTbt,
+    /// 400 档 tick-by-tick 增量 (需要 VIP 等级)
+    BooksL2Tbt,
+    /// 聚合成交 (同一主动单的多笔成交合并推送)
+    Trades,
 }
 
 impl ChannelType {
+    /// 深度频道名 -> 类型 (配置项 ORDER_BOOK_CHANNEL)
+    pub fn book_channel(name: &str) -> Option<Self> {
+        match name {
+            "books" => Some(ChannelType::Books),
+            "books5" => Some(ChannelType::Books5),
+            "bbo-tbt" => Some(ChannelType::BboTbt),
+            "books-l2-tbt" => Some(ChannelType::BooksL2Tbt),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             ChannelType::Tickers => "tickers",
             ChannelType::Account => "account",
+            ChannelType::Orders => "orders",
+            ChannelType::Instruments => "instruments",
+            ChannelType::Books => "books",
+            ChannelType::Books5 => "books5",
+            ChannelType::BboTbt => "bbo-tbt",
+            ChannelType::BooksL2Tbt => "books-l2-tbt",
+            ChannelType::Trades => "trades",
         }
     }
 }
@@ -51,96 +165,611 @@ pub struct WsRouter {
     // 🔔 新增: 错误码
     pub code: Option<String>,
     pub msg: Option<String>,
+    // 🔔 新增: 连接 ID (login / error 响应携带，提交工单时需要)
+    #[serde(rename = "connId")]
+    pub conn_id: Option<String>,
+    // 🔔 新增: 请求 ID (订阅 / 交易请求原样回显，用于关联响应)
+    pub id: Option<String>,
+    // 🔔 新增: 交易类请求 (order 等) 的响应携带 op
+    pub op: Option<String>,
+    // 🔔 新增: 深度频道的推送类型 (snapshot / update)
+    pub action: Option<String>,
 
     // 保持使用 Box 指针解决 size unknown 问题
     pub data: Option<Box<serde_json::value::RawValue>>,
 }
 
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize, Serialize)]
+impl WsRouter {
+    pub fn parse(text: &str) -> OkxResult<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    /// event=error 时转换为类型化错误
+    pub fn error(&self) -> Option<OkxError> {
+        if self.event.as_deref() != Some("error") {
+            return None;
+        }
+        Some(OkxError::from_api(
+            self.code.as_deref().unwrap_or_default(),
+            self.msg.as_deref().unwrap_or_default(),
+            false,
+        ))
+    }
+}
+
+/// 📮 频道参数：订阅请求与推送 / 确认消息共用
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct WsArg {
     pub channel: String,
 
-    #[serde(rename = "instId")]
+    #[serde(rename = "instType", skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<String>,
+    #[serde(rename = "instId", skip_serializing_if = "Option::is_none")]
     pub inst_id: Option<String>,
-    pub instId: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ccy: Option<String>,
 }
 
+impl WsArg {
+    /// 按品种订阅 (tickers 等)
+    pub fn for_inst(channel: ChannelType, inst_id: &str) -> Self {
+        WsArg { channel: channel.as_str().to_string(), inst_type: None, inst_id: Some(inst_id.to_string()), ccy: None }
+    }
+
+    /// 按产品类型订阅 (orders 等)，"ANY" 表示全部类型
+    pub fn for_inst_type(channel: ChannelType, inst_type: &str) -> Self {
+        WsArg { channel: channel.as_str().to_string(), inst_type: Some(inst_type.to_string()), inst_id: None, ccy: None }
+    }
+
+    /// K 线频道 (bar 需在 CANDLE_BARS 内，由配置加载时校验)
+    pub fn candle(bar: &str, inst_id: &str) -> Self {
+        WsArg { channel: format!("candle{}", bar), inst_type: None, inst_id: Some(inst_id.to_string()), ccy: None }
+    }
+
+    /// "candle1m" -> "1m"
+    pub fn candle_bar(&self) -> Option<&str> {
+        self.channel.strip_prefix("candle").filter(|bar| CANDLE_BARS.contains(bar))
+    }
+
+    /// 按币种订阅 (account)，ccy 为空表示全部币种
+    pub fn for_ccy(channel: ChannelType, ccy: Option<&str>) -> Self {
+        WsArg { channel: channel.as_str().to_string(), inst_type: None, inst_id: None, ccy: ccy.map(str::to_string) }
+    }
+}
+
+impl std::fmt::Display for WsArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.channel)?;
+        if let Some(inst_type) = &self.inst_type { write!(f, ":{}", inst_type)?; }
+        if let Some(inst_id) = &self.inst_id { write!(f, ":{}", inst_id)?; }
+        if let Some(ccy) = &self.ccy { write!(f, ":{}", ccy)?; }
+        Ok(())
+    }
+}
+
+/// 批量订阅 / 取消订阅请求
+pub fn create_subscription_packet(op: &str, id: &str, args: &[WsArg]) -> String {
+    serde_json::json!({
+        "id": id,
+        "op": op,
+        "args": args,
+    }).to_string()
+}
+
 // ==========================================
 // ⚔️ 交易协议
 // ==========================================
 
+/// 买卖方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+/// 持仓方向：买卖模式用 Net，开平仓模式用 Long / Short (仅衍生品)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PosSide {
+    #[allow(dead_code)] // 暂时抑制警告
+    Net,
+    #[allow(dead_code)] // 暂时抑制警告
+    Long,
+    #[allow(dead_code)] // 暂时抑制警告
+    Short,
+}
+
+/// 订单类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrdType {
+    Market,
+    #[allow(dead_code)] // 暂时抑制警告
+    Limit,
+    /// 只做 Maker，会立即成交则被撤销
+    #[allow(dead_code)] // 暂时抑制警告
+    PostOnly,
+    /// 立即成交并取消剩余
+    #[allow(dead_code)] // 暂时抑制警告
+    Ioc,
+    /// 全部成交或立即取消
+    #[allow(dead_code)] // 暂时抑制警告
+    Fok,
+    /// 市价委托立即成交并取消剩余 (仅交割 / 永续)
+    OptimalLimitIoc,
+}
+
+impl OrdType {
+    /// 需要指定价格的类型
+    fn requires_px(&self) -> bool {
+        matches!(self, OrdType::Limit | OrdType::PostOnly | OrdType::Ioc | OrdType::Fok)
+    }
+}
+
+/// 交易模式：现货非杠杆用 Cash，杠杆 / 衍生品用 Cross / Isolated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TdMode {
+    Cash,
+    Cross,
+    #[allow(dead_code)] // 暂时抑制警告
+    Isolated,
+}
+
+/// 自成交保护 (Self Trade Prevention)：撤掉哪一方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StpMode {
+    #[serde(rename = "cancel_maker")]
+    #[allow(dead_code)] // 暂时抑制警告
+    Maker,
+    #[serde(rename = "cancel_taker")]
+    #[allow(dead_code)] // 暂时抑制警告
+    Taker,
+    #[serde(rename = "cancel_both")]
+    #[allow(dead_code)] // 暂时抑制警告
+    Both,
+}
+
 #[derive(Serialize)]
-#[allow(non_snake_case)]
-struct OrderRequest<'a> {
+struct OpRequest<T: Serialize> {
     id: String,
     op: &'static str,
-    args: [OrderArgs<'a>; 1],
+    args: Vec<T>,
 }
 
 #[derive(Serialize)]
 #[allow(non_snake_case)]
 struct OrderArgs<'a> {
     clOrdId: String,
-    side: &'a str,
-    posSide: &'a str,
-    ordType: &'a str,
+    side: Side,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    posSide: Option<PosSide>,
+    ordType: OrdType,
     instId: &'a str,
-    sz: &'a str,
-    tdMode: &'a str,
-}
-
-pub fn create_order_packet(inst_id: &str, side: &str, size: &str, pos_side: Option<&str>) -> String {
-    let (ord_type, td_mode) = if inst_id.contains("SWAP") {
-        ("market", "cross")
-    } else {
-        ("market", "cash")
-    };
-
-    let final_pos_side = pos_side.unwrap_or("net");
-    let nonce = ORDER_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
-    let now_secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-    let cl_ord_id = format!("snip{:x}{}", now_secs % 10000, nonce);
-    let req_id = Uuid::new_v4().to_string();
-
-    let request = OrderRequest {
-        id: req_id,
-        op: "order",
-        args: [OrderArgs {
+    sz: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    px: Option<String>,
+    tdMode: TdMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tgtCcy: Option<&'static str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reduceOnly: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ccy: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stpMode: Option<StpMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+}
+
+/// 📏 下单数量的含义，由 OrderRequest 按产品类型换算成 sz / tgtCcy
+/// "花 25 USDT" 在现货和合约上都用 Quote 表达，不再依赖 OKX 的默认解释
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderSize {
+    /// 基础币数量 (如 0.5 SOL)
+    Base(f64),
+    /// 计价币金额 (如 25 USDT)；ref_px 仅用于换算合约张数与最小下单量校验
+    Quote { amount: f64, ref_px: f64 },
+    /// 合约张数 (仅衍生品)
+    #[allow(dead_code)] // 暂时抑制警告
+    Contracts(f64),
+}
+
+impl OrderSize {
+    /// 换算为 (sz, tgtCcy)，并按 lotSz 取整 / 校验最小下单量
+    /// tgtCcy 只对现货市价单生效；现货限价单的 sz 一律是基础币数量，按委托价换算
+    fn resolve(&self, inst: &Instrument, limit_px: Option<f64>) -> OkxResult<(String, Option<&'static str>)> {
+        if inst.inst_type == InstType::Spot {
+            return match (*self, limit_px) {
+                (OrderSize::Base(qty), None) => Ok((inst.format_size(inst.check_size(qty)?), Some("base_ccy"))),
+                (OrderSize::Base(qty), Some(_)) => Ok((inst.format_size(inst.check_size(qty)?), None)),
+                (OrderSize::Quote { amount, ref_px }, None) => {
+                    if ref_px > 0.0 {
+                        inst.check_size(amount / ref_px)?;
+                    }
+                    Ok((format!("{}", amount), Some("quote_ccy")))
+                }
+                (OrderSize::Quote { amount, .. }, Some(px)) => Ok((inst.format_size(inst.check_size(amount / px)?), None)),
+                (OrderSize::Contracts(_), _) => Err(OkxError::InvalidOrder(format!("{} 是现货，不能按张数下单", inst.inst_id))),
+            };
+        }
+
+        let contracts = match *self {
+            OrderSize::Contracts(n) => n,
+            OrderSize::Base(qty) => qty / inst.contract_value_in_base(limit_px)?,
+            OrderSize::Quote { amount, ref_px } => {
+                let px = limit_px.unwrap_or(ref_px);
+                amount / (inst.contract_value_in_base(Some(px))? * px)
+            }
+        };
+        if !contracts.is_finite() {
+            return Err(OkxError::InvalidOrder(format!("{} 无法换算合约张数: {:?}", inst.inst_id, self)));
+        }
+        Ok((inst.format_size(inst.check_size(contracts)?), None))
+    }
+}
+
+/// 🎯 请求涉及的订单 (批量请求有多个)
+#[derive(Debug, Clone)]
+pub struct OrderTarget {
+    pub cl_ord_id: String,
+    pub inst_id: String,
+    // 撤单不带方向
+    pub side: Option<Side>,
+}
+
+/// 📦 已构造的交易请求：payload 直接发送，req_id / op / targets 交给订单管理器关联回执
+#[derive(Debug, Clone)]
+pub struct OpPacket {
+    pub req_id: String,
+    pub op: &'static str,
+    pub targets: Vec<OrderTarget>,
+    pub payload: String,
+}
+
+impl OpPacket {
+    fn new<T: Serialize>(op: &'static str, args: Vec<T>, targets: Vec<OrderTarget>) -> OkxResult<Self> {
+        let req_id = Uuid::new_v4().to_string();
+        let payload = serde_json::to_string(&OpRequest { id: req_id.clone(), op, args })?;
+        Ok(OpPacket { req_id, op, targets, payload })
+    }
+
+    /// 单笔请求的 clOrdId
+    pub fn cl_ord_id(&self) -> &str {
+        self.targets.first().map(|t| t.cl_ord_id.as_str()).unwrap_or_default()
+    }
+}
+
+// 批量请求单次最多 20 笔
+#[allow(dead_code)] // 暂时抑制警告
+const MAX_BATCH_SIZE: usize = 20;
+
+#[allow(dead_code)] // 暂时抑制警告
+fn check_batch_len(op: &str, len: usize) -> OkxResult<()> {
+    if len == 0 || len > MAX_BATCH_SIZE {
+        return Err(OkxError::InvalidOrder(format!("{} 需要 1-{} 笔，实际 {}", op, MAX_BATCH_SIZE, len)));
+    }
+    Ok(())
+}
+
+/// 🧾 [下单构造器] 先选订单类型 (market / limit / post_only ...)，再按需叠加可选参数
+/// 非法组合在 build() 时本地拒绝，不浪费一次交易所请求
+#[derive(Debug, Clone)]
+pub struct OrderRequest<'a> {
+    inst: &'a Instrument,
+    side: Side,
+    ord_type: OrdType,
+    size: OrderSize,
+    px: Option<f64>,
+    pos_side: Option<PosSide>,
+    td_mode: Option<TdMode>,
+    reduce_only: bool,
+    ccy: Option<&'a str>,
+    stp_mode: Option<StpMode>,
+    tag: Option<&'a str>,
+    // 写入 clOrdId 的策略 / 信号代码，便于事后追溯
+    origin: (&'a str, &'a str),
+}
+
+impl<'a> OrderRequest<'a> {
+    fn new(inst: &'a Instrument, side: Side, ord_type: OrdType, size: OrderSize, px: Option<f64>) -> Self {
+        OrderRequest {
+            inst, side, ord_type, size, px,
+            pos_side: None,
+            td_mode: None,
+            reduce_only: false,
+            ccy: None,
+            stp_mode: None,
+            tag: None,
+            origin: (order_id::UNKNOWN_CODE, order_id::UNKNOWN_CODE),
+        }
+    }
+
+    pub fn market(inst: &'a Instrument, side: Side, size: OrderSize) -> Self {
+        Self::new(inst, side, OrdType::Market, size, None)
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn limit(inst: &'a Instrument, side: Side, size: OrderSize, px: f64) -> Self {
+        Self::new(inst, side, OrdType::Limit, size, Some(px))
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn post_only(inst: &'a Instrument, side: Side, size: OrderSize, px: f64) -> Self {
+        Self::new(inst, side, OrdType::PostOnly, size, Some(px))
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn ioc(inst: &'a Instrument, side: Side, size: OrderSize, px: f64) -> Self {
+        Self::new(inst, side, OrdType::Ioc, size, Some(px))
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn fok(inst: &'a Instrument, side: Side, size: OrderSize, px: f64) -> Self {
+        Self::new(inst, side, OrdType::Fok, size, Some(px))
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn optimal_limit_ioc(inst: &'a Instrument, side: Side, size: OrderSize) -> Self {
+        Self::new(inst, side, OrdType::OptimalLimitIoc, size, None)
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn pos_side(mut self, pos_side: PosSide) -> Self {
+        self.pos_side = Some(pos_side);
+        self
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn td_mode(mut self, td_mode: TdMode) -> Self {
+        self.td_mode = Some(td_mode);
+        self
+    }
+
+    /// 逐仓
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn isolated(self) -> Self {
+        self.td_mode(TdMode::Isolated)
+    }
+
+    /// 只减仓
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn reduce_only(mut self) -> Self {
+        self.reduce_only = true;
+        self
+    }
+
+    /// 保证金币种 (杠杆交易)
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn margin_ccy(mut self, ccy: &'a str) -> Self {
+        self.ccy = Some(ccy);
+        self
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn stp_mode(mut self, stp_mode: StpMode) -> Self {
+        self.stp_mode = Some(stp_mode);
+        self
+    }
+
+    /// 订单来源 (2 位策略代码 + 2 位信号代码)，编码进 clOrdId
+    pub fn origin(mut self, strategy: &'a str, signal: &'a str) -> Self {
+        self.origin = (strategy, signal);
+        self
+    }
+
+    /// 订单标签 (1-16 位字母数字)
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn tag(mut self, tag: &'a str) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// ✅ 校验组合、按精度取整
+    fn to_args(&self) -> OkxResult<(OrderArgs<'a>, OrderTarget)> {
+        let inst = self.inst;
+        let invalid = |reason: &str| Err(OkxError::InvalidOrder(format!("{} {}", inst.inst_id, reason)));
+
+        if !inst.is_tradable() {
+            return invalid(&format!("当前状态 {:?}，不可交易", inst.state));
+        }
+        let is_spot = inst.inst_type == InstType::Spot;
+        let td_mode = self.td_mode.unwrap_or(inst.inst_type.default_td_mode());
+        if !is_spot && td_mode == TdMode::Cash {
+            return invalid("衍生品不支持 cash 交易模式");
+        }
+        if is_spot && matches!(self.pos_side, Some(PosSide::Long | PosSide::Short)) {
+            return invalid("现货不支持 long/short 持仓方向");
+        }
+        if is_spot && self.ord_type == OrdType::OptimalLimitIoc {
+            return invalid("optimal_limit_ioc 仅支持交割 / 永续");
+        }
+        if self.reduce_only && td_mode == TdMode::Cash {
+            return invalid("现货非杠杆交易不支持 reduceOnly");
+        }
+        if self.ccy.is_some() && td_mode == TdMode::Cash {
+            return invalid("只有杠杆交易才能指定保证金币种");
+        }
+        if let Some(tag) = self.tag {
+            if tag.is_empty() || tag.len() > 16 || !tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return invalid("tag 必须是 1-16 位字母数字");
+            }
+        }
+
+        // 限价类订单价格按 tickSz 取整 (买单向下、卖单向上)
+        let px = match (self.ord_type.requires_px(), self.px) {
+            (true, Some(px)) if px > 0.0 && px.is_finite() => Some(inst.round_price(px, self.side)),
+            (true, _) => return invalid("限价类订单必须指定有效价格"),
+            (false, _) => None,
+        };
+        let (sz, tgt_ccy) = self.size.resolve(inst, px)?;
+
+        let cl_ord_id = order_id::next(self.origin.0, self.origin.1)?;
+
+        let target = OrderTarget { cl_ord_id: cl_ord_id.clone(), inst_id: inst.inst_id.clone(), side: Some(self.side) };
+        let args = OrderArgs {
             clOrdId: cl_ord_id,
-            side,
-            posSide: final_pos_side,
-            ordType: ord_type,
-            instId: inst_id,
-            sz: size,
+            side: self.side,
+            posSide: self.pos_side,
+            ordType: self.ord_type,
+            instId: &inst.inst_id,
+            sz,
+            px: px.map(|p| inst.format_price(p)),
             tdMode: td_mode,
-        }],
-    };
+            tgtCcy: tgt_ccy,
+            reduceOnly: self.reduce_only,
+            ccy: self.ccy,
+            stpMode: self.stp_mode,
+            tag: self.tag,
+        };
+        Ok((args, target))
+    }
+
+    /// ✅ 生成 op=order 请求
+    pub fn build(self) -> OkxResult<OpPacket> {
+        let (args, target) = self.to_args()?;
+        OpPacket::new("order", vec![args], vec![target])
+    }
+}
 
-    serde_json::to_string(&request).unwrap()
+/// 📚 批量下单 (batch-orders)，任意一笔本地校验失败则整批不发
+#[allow(dead_code)] // 暂时抑制警告
+pub fn create_batch_orders_packet(orders: &[OrderRequest]) -> OkxResult<OpPacket> {
+    check_batch_len("batch-orders", orders.len())?;
+    let (args, targets): (Vec<_>, Vec<_>) = orders.iter().map(|o| o.to_args()).collect::<OkxResult<Vec<_>>>()?.into_iter().unzip();
+    OpPacket::new("batch-orders", args, targets)
 }
 
-pub fn create_subscribe_packet(channel: ChannelType, inst_id: &str) -> String {
-    if channel.as_str() == "account" {
-        serde_json::json!({
-            "op": "subscribe",
-            "args": [{
-                "channel": channel.as_str(),
-                "ccy": "USDT"
-            }]
-        }).to_string()
-    } else {
-        serde_json::json!({
-            "op": "subscribe",
-            "args": [{
-                "channel": channel.as_str(),
-                "instId": inst_id
-            }]
-        }).to_string()
+// ==========================================
+// ✂️ 撤单 / 改单
+// ==========================================
+
+/// 撤单目标 (本策略的订单一律用 clOrdId 定位)
+#[derive(Debug, Clone, Serialize)]
+pub struct CancelRequest<'a> {
+    #[serde(rename = "instId")]
+    pub inst_id: &'a str,
+    #[serde(rename = "clOrdId")]
+    pub cl_ord_id: &'a str,
+}
+
+impl CancelRequest<'_> {
+    fn target(&self) -> OrderTarget {
+        OrderTarget { cl_ord_id: self.cl_ord_id.to_string(), inst_id: self.inst_id.to_string(), side: None }
+    }
+}
+
+pub fn create_cancel_packet(req: CancelRequest) -> OkxResult<OpPacket> {
+    let target = req.target();
+    OpPacket::new("cancel-order", vec![req], vec![target])
+}
+
+#[allow(dead_code)] // 暂时抑制警告
+pub fn create_batch_cancel_packet(reqs: &[CancelRequest]) -> OkxResult<OpPacket> {
+    check_batch_len("batch-cancel-orders", reqs.len())?;
+    let targets = reqs.iter().map(CancelRequest::target).collect();
+    OpPacket::new("batch-cancel-orders", reqs.to_vec(), targets)
+}
+
+#[derive(Serialize)]
+#[allow(non_snake_case)]
+#[allow(dead_code)] // 暂时抑制警告
+struct AmendArgs<'a> {
+    instId: &'a str,
+    clOrdId: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    newSz: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    newPx: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    cxlOnFail: bool,
+}
+
+/// ✏️ [改单构造器] 新数量 (基础币 / 合约张数，按 lotSz 取整) 与新价格至少指定一项
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // 暂时抑制警告
+pub struct AmendRequest<'a> {
+    inst: &'a Instrument,
+    cl_ord_id: &'a str,
+    side: Side,
+    new_sz: Option<f64>,
+    new_px: Option<f64>,
+    cxl_on_fail: bool,
+}
+
+impl<'a> AmendRequest<'a> {
+    /// side 用于新价格的取整方向
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn new(inst: &'a Instrument, cl_ord_id: &'a str, side: Side) -> Self {
+        AmendRequest { inst, cl_ord_id, side, new_sz: None, new_px: None, cxl_on_fail: false }
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn new_size(mut self, sz: f64) -> Self {
+        self.new_sz = Some(sz);
+        self
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn new_px(mut self, px: f64) -> Self {
+        self.new_px = Some(px);
+        self
+    }
+
+    /// 改单失败时自动撤单
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn cancel_on_fail(mut self) -> Self {
+        self.cxl_on_fail = true;
+        self
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    fn to_args(&self) -> OkxResult<(AmendArgs<'a>, OrderTarget)> {
+        let inst = self.inst;
+        if self.new_sz.is_none() && self.new_px.is_none() {
+            return Err(OkxError::InvalidOrder(format!("{} 改单至少需要新数量或新价格", inst.inst_id)));
+        }
+        let new_sz = self.new_sz.map(|sz| inst.check_size(sz).map(|sz| inst.format_size(sz))).transpose()?;
+        let new_px = match self.new_px {
+            Some(px) if px > 0.0 && px.is_finite() => Some(inst.format_price(inst.round_price(px, self.side))),
+            Some(_) => return Err(OkxError::InvalidOrder(format!("{} 改单价格无效", inst.inst_id))),
+            None => None,
+        };
+        let target = OrderTarget { cl_ord_id: self.cl_ord_id.to_string(), inst_id: inst.inst_id.clone(), side: Some(self.side) };
+        Ok((AmendArgs { instId: &inst.inst_id, clOrdId: self.cl_ord_id, newSz: new_sz, newPx: new_px, cxlOnFail: self.cxl_on_fail }, target))
+    }
+
+    #[allow(dead_code)] // 暂时抑制警告
+    pub fn build(self) -> OkxResult<OpPacket> {
+        let (args, target) = self.to_args()?;
+        OpPacket::new("amend-order", vec![args], vec![target])
     }
 }
 
+#[allow(dead_code)] // 暂时抑制警告
+pub fn create_batch_amend_packet(reqs: &[AmendRequest]) -> OkxResult<OpPacket> {
+    check_batch_len("batch-amend-orders", reqs.len())?;
+    let (args, targets): (Vec<_>, Vec<_>) = reqs.iter().map(|r| r.to_args()).collect::<OkxResult<Vec<_>>>()?.into_iter().unzip();
+    OpPacket::new("batch-amend-orders", args, targets)
+}
+
+/// 🧨 撤销某个交易品种下全部 MMP 挂单 (OKX 仅对组合保证金模式下的期权生效)
+#[allow(dead_code)] // 暂时抑制警告
+pub fn create_mass_cancel_packet(inst_type: InstType, inst_family: &str) -> OkxResult<OpPacket> {
+    let args = serde_json::json!({ "instType": inst_type.as_str(), "instFamily": inst_family });
+    OpPacket::new("mass-cancel", vec![args], Vec::new())
+}
+
 // ==========================================
 // 💰 账户数据结构
 // ==========================================
@@ -157,4 +786,234 @@ pub struct BalanceDetail {
     #[allow(dead_code)]
     #[serde(rename = "cashBal")]
     pub cash_bal: String,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn spot() -> Instrument {
+        serde_json::from_value(json!({
+            "instId": "BTC-USDT", "instType": "SPOT", "state": "live",
+            "tickSz": "0.1", "lotSz": "0.0001", "minSz": "0.0001",
+        })).unwrap()
+    }
+
+    fn swap() -> Instrument {
+        serde_json::from_value(json!({
+            "instId": "BTC-USDT-SWAP", "instType": "SWAP", "state": "live",
+            "tickSz": "0.1", "lotSz": "0.01", "minSz": "0.01", "ctVal": "0.01", "ctValCcy": "BTC",
+        })).unwrap()
+    }
+
+    fn inverse() -> Instrument {
+        serde_json::from_value(json!({
+            "instId": "BTC-USD-SWAP", "instType": "SWAP", "state": "live",
+            "tickSz": "0.1", "lotSz": "1", "minSz": "1", "ctVal": "100", "ctValCcy": "USD",
+        })).unwrap()
+    }
+
+    fn args_of(packet: &OpPacket) -> Vec<Value> {
+        let payload: Value = serde_json::from_str(&packet.payload).unwrap();
+        assert_eq!(payload["id"], packet.req_id.as_str());
+        assert_eq!(payload["op"], packet.op);
+        payload["args"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn order_payload_shape() {
+        let inst = spot();
+        let packet = OrderRequest::limit(&inst, Side::Buy, OrderSize::Base(0.5), 100.05).origin("fc", "en").build().unwrap();
+        assert_eq!(packet.op, "order");
+        assert_eq!(packet.targets.len(), 1);
+
+        let args = args_of(&packet);
+        assert_eq!(args, vec![json!({
+            "clOrdId": packet.cl_ord_id(), "side": "buy", "ordType": "limit", "instId": "BTC-USDT",
+            "sz": "0.5000", "px": "100.0", "tdMode": "cash",
+        })]);
+        let info = order_id::decode(packet.cl_ord_id()).unwrap();
+        assert_eq!((info.strategy.as_str(), info.signal.as_str()), ("fc", "en"));
+    }
+
+    #[test]
+    fn optional_fields_are_emitted_when_set() {
+        let inst = swap();
+        let packet = OrderRequest::market(&inst, Side::Sell, OrderSize::Contracts(1.0))
+            .pos_side(PosSide::Short).isolated().reduce_only().margin_ccy("USDT").stp_mode(StpMode::Maker).tag("sniper1")
+            .build().unwrap();
+        let arg = &args_of(&packet)[0];
+        assert_eq!(arg["ordType"], "market");
+        assert_eq!(arg["posSide"], "short");
+        assert_eq!(arg["tdMode"], "isolated");
+        assert_eq!(arg["reduceOnly"], true);
+        assert_eq!(arg["ccy"], "USDT");
+        assert_eq!(arg["stpMode"], "cancel_maker");
+        assert_eq!(arg["tag"], "sniper1");
+        assert!(arg.get("px").is_none());
+        assert!(arg.get("tgtCcy").is_none());
+    }
+
+    #[test]
+    fn ord_type_and_price_rounding() {
+        let inst = spot();
+        let cases = [
+            (OrderRequest::post_only(&inst, Side::Buy, OrderSize::Base(1.0), 100.07), "post_only", "100.0"),
+            (OrderRequest::post_only(&inst, Side::Sell, OrderSize::Base(1.0), 100.03), "post_only", "100.1"),
+            (OrderRequest::ioc(&inst, Side::Buy, OrderSize::Base(1.0), 99.99), "ioc", "99.9"),
+            (OrderRequest::fok(&inst, Side::Sell, OrderSize::Base(1.0), 99.99), "fok", "100.0"),
+        ];
+        for (req, ord_type, px) in cases {
+            let arg = &args_of(&req.build().unwrap())[0];
+            assert_eq!((arg["ordType"].as_str(), arg["px"].as_str()), (Some(ord_type), Some(px)));
+        }
+    }
+
+    #[test]
+    fn limit_order_requires_valid_px() {
+        let inst = spot();
+        for px in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let err = OrderRequest::limit(&inst, Side::Buy, OrderSize::Base(1.0), px).build().unwrap_err();
+            assert!(matches!(err, OkxError::InvalidOrder(_)), "px {}", px);
+        }
+        let mut req = OrderRequest::limit(&inst, Side::Buy, OrderSize::Base(1.0), 100.0);
+        req.px = None;
+        assert!(matches!(req.build(), Err(OkxError::InvalidOrder(_))));
+    }
+
+    #[test]
+    fn invalid_combinations_are_rejected_locally() {
+        let spot = spot();
+        let swap = swap();
+        let rejected = [
+            OrderRequest::market(&spot, Side::Buy, OrderSize::Base(1.0)).pos_side(PosSide::Long),
+            OrderRequest::market(&spot, Side::Sell, OrderSize::Base(1.0)).reduce_only(),
+            OrderRequest::market(&spot, Side::Buy, OrderSize::Base(1.0)).margin_ccy("USDT"),
+            OrderRequest::market(&spot, Side::Buy, OrderSize::Base(1.0)).tag("bad-tag"),
+            OrderRequest::optimal_limit_ioc(&spot, Side::Buy, OrderSize::Base(1.0)),
+            OrderRequest::market(&swap, Side::Buy, OrderSize::Contracts(1.0)).td_mode(TdMode::Cash),
+        ];
+        for req in rejected {
+            assert!(matches!(req.build(), Err(OkxError::InvalidOrder(_))));
+        }
+    }
+
+    #[test]
+    fn batch_orders_are_limited_to_20() {
+        let inst = spot();
+        let orders: Vec<OrderRequest> = (0..21).map(|_| OrderRequest::market(&inst, Side::Buy, OrderSize::Base(1.0))).collect();
+        assert!(matches!(create_batch_orders_packet(&orders), Err(OkxError::InvalidOrder(_))));
+        assert!(matches!(create_batch_orders_packet(&[]), Err(OkxError::InvalidOrder(_))));
+
+        let packet = create_batch_orders_packet(&orders[..20]).unwrap();
+        assert_eq!(packet.op, "batch-orders");
+        let args = args_of(&packet);
+        assert_eq!(args.len(), 20);
+        let ids: Vec<&str> = packet.targets.iter().map(|t| t.cl_ord_id.as_str()).collect();
+        assert_eq!(args.iter().map(|a| a["clOrdId"].as_str().unwrap()).collect::<Vec<_>>(), ids);
+
+        // 任意一笔校验失败则整批不发
+        let mut mixed = orders[..2].to_vec();
+        mixed.push(OrderRequest::market(&inst, Side::Buy, OrderSize::Base(0.00001)));
+        assert!(create_batch_orders_packet(&mixed).is_err());
+    }
+
+    #[test]
+    fn cancel_payloads() {
+        let packet = create_cancel_packet(CancelRequest { inst_id: "BTC-USDT", cl_ord_id: "abc" }).unwrap();
+        assert_eq!(packet.op, "cancel-order");
+        assert_eq!(args_of(&packet), vec![json!({ "instId": "BTC-USDT", "clOrdId": "abc" })]);
+
+        let reqs: Vec<CancelRequest> = (0..21).map(|_| CancelRequest { inst_id: "BTC-USDT", cl_ord_id: "abc" }).collect();
+        assert!(create_batch_cancel_packet(&reqs).is_err());
+        let packet = create_batch_cancel_packet(&reqs[..20]).unwrap();
+        assert_eq!((packet.op, args_of(&packet).len(), packet.targets.len()), ("batch-cancel-orders", 20, 20));
+    }
+
+    #[test]
+    fn amend_payloads() {
+        let inst = spot();
+        assert!(AmendRequest::new(&inst, "abc", Side::Buy).build().is_err());
+
+        let packet = AmendRequest::new(&inst, "abc", Side::Sell).new_size(0.12345).new_px(100.01).cancel_on_fail().build().unwrap();
+        assert_eq!(packet.op, "amend-order");
+        assert_eq!(args_of(&packet), vec![json!({
+            "instId": "BTC-USDT", "clOrdId": "abc", "newSz": "0.1234", "newPx": "100.1", "cxlOnFail": true,
+        })]);
+
+        let packet = AmendRequest::new(&inst, "abc", Side::Buy).new_px(100.01).build().unwrap();
+        assert_eq!(args_of(&packet), vec![json!({ "instId": "BTC-USDT", "clOrdId": "abc", "newPx": "100.0" })]);
+
+        let reqs: Vec<AmendRequest> = (0..21).map(|_| AmendRequest::new(&inst, "abc", Side::Buy).new_px(100.0)).collect();
+        assert!(create_batch_amend_packet(&reqs).is_err());
+        assert_eq!(args_of(&create_batch_amend_packet(&reqs[..20]).unwrap()).len(), 20);
+    }
+
+    #[test]
+    fn mass_cancel_args() {
+        let packet = create_mass_cancel_packet(InstType::Option, "BTC-USD").unwrap();
+        assert_eq!(packet.op, "mass-cancel");
+        assert!(packet.targets.is_empty());
+        assert_eq!(args_of(&packet), vec![json!({ "instType": "OPTION", "instFamily": "BTC-USD" })]);
+    }
+
+    #[test]
+    fn order_size_resolve_table() {
+        let (spot, linear, inverse) = (spot(), swap(), inverse());
+        let base = Some("base_ccy");
+        let quote = Some("quote_ccy");
+        let cases = [
+            // 现货市价: 基础币 / 计价币数量原样带 tgtCcy
+            (&spot, OrderSize::Base(0.12345), None, ("0.1234", base)),
+            (&spot, OrderSize::Quote { amount: 25.0, ref_px: 100.0 }, None, ("25", quote)),
+            // 现货限价: 一律换算成基础币数量，不带 tgtCcy
+            (&spot, OrderSize::Base(0.5), Some(100.0), ("0.5000", None)),
+            (&spot, OrderSize::Quote { amount: 25.0, ref_px: 90.0 }, Some(100.0), ("0.2500", None)),
+            // 正向合约: 1 张 = 0.01 BTC
+            (&linear, OrderSize::Contracts(3.0), None, ("3.00", None)),
+            (&linear, OrderSize::Base(0.055), None, ("5.50", None)),
+            (&linear, OrderSize::Quote { amount: 1000.0, ref_px: 50_000.0 }, None, ("2.00", None)),
+            (&linear, OrderSize::Quote { amount: 1000.0, ref_px: 50_000.0 }, Some(40_000.0), ("2.50", None)),
+            // 反向合约: 1 张 = 100 USD
+            (&inverse, OrderSize::Quote { amount: 1050.0, ref_px: 50_000.0 }, None, ("10", None)),
+            (&inverse, OrderSize::Base(0.1), Some(50_000.0), ("50", None)),
+        ];
+        for (inst, size, px, (sz, tgt_ccy)) in cases {
+            let resolved = size.resolve(inst, px).unwrap();
+            assert_eq!((resolved.0.as_str(), resolved.1), (sz, tgt_ccy), "{} {:?} @ {:?}", inst.inst_id, size, px);
+        }
+    }
+
+    #[test]
+    fn order_size_resolve_rejects() {
+        let (spot, linear, inverse) = (spot(), swap(), inverse());
+        let cases = [
+            // 低于最小下单量 (取整到 lotSz 后为 0)
+            (&spot, OrderSize::Base(0.00009), None),
+            (&spot, OrderSize::Quote { amount: 0.005, ref_px: 100.0 }, None),
+            (&linear, OrderSize::Contracts(0.009), None),
+            // 现货不能按张数下单
+            (&spot, OrderSize::Contracts(1.0), None),
+            // 反向合约按基础币换算需要价格
+            (&inverse, OrderSize::Base(0.1), None),
+            (&inverse, OrderSize::Quote { amount: 1000.0, ref_px: 0.0 }, None),
+        ];
+        for (inst, size, px) in cases {
+            assert!(matches!(size.resolve(inst, px), Err(OkxError::InvalidOrder(_))), "{} {:?} @ {:?}", inst.inst_id, size, px);
+        }
+    }
+
+    #[test]
+    fn business_login_depends_on_channels() {
+        let candles = vec![WsArg::candle("1m", "BTC-USDT"), WsArg::candle("1Hutc", "ETH-USDT")];
+        assert!(!Endpoint::Business.requires_login(&candles));
+        assert!(!Endpoint::Business.requires_login(&[]));
+
+        let mut with_algo = candles.clone();
+        with_algo.push(WsArg { channel: "orders-algo".into(), inst_type: Some("ANY".into()), inst_id: None, ccy: None });
+        assert!(Endpoint::Business.requires_login(&with_algo));
+
+        assert!(!Endpoint::Public.requires_login(&with_algo));
+        assert!(Endpoint::Private.requires_login(&[]));
+    }
+}
@@ -0,0 +1,116 @@
+// src/okx/proxy.rs
+
+use crate::okx::error::{OkxError, OkxResult};
+use async_http_proxy::{http_connect_tokio, http_connect_tokio_with_basic_auth};
+use percent_encoding::percent_decode_str;
+use std::fmt;
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use url::Url;
+
+// 未写端口时的默认值
+const DEFAULT_HTTP_PORT: u16 = 80;
+const DEFAULT_SOCKS5_PORT: u16 = 1080;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    /// HTTP CONNECT 隧道
+    Http,
+    /// socks5:// 本地解析域名；socks5h:// 交给代理远程解析
+    Socks5 { remote_dns: bool },
+}
+
+/// 🌐 [代理配置] 由 PROXY_URL 解析而来
+/// 支持: http://[user:pass@]host[:port] / socks5://... / socks5h://...
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    credentials: Option<(String, String)>,
+}
+
+impl ProxyConfig {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let url = Url::parse(raw.trim()).map_err(|e| format!("无法解析代理地址: {}", e))?;
+
+        let (kind, default_port) = match url.scheme() {
+            "http" => (ProxyKind::Http, DEFAULT_HTTP_PORT),
+            "socks5" => (ProxyKind::Socks5 { remote_dns: false }, DEFAULT_SOCKS5_PORT),
+            "socks5h" => (ProxyKind::Socks5 { remote_dns: true }, DEFAULT_SOCKS5_PORT),
+            other => return Err(format!("不支持的代理协议 {}://，仅支持 http / socks5 / socks5h", other)),
+        };
+
+        let host = url.host_str()
+            .filter(|h| !h.is_empty())
+            .ok_or_else(|| "代理地址缺少主机名".to_string())?
+            .trim_matches(|c| c == '[' || c == ']') // IPv6 字面量
+            .to_string();
+        let port = url.port().unwrap_or(default_port);
+
+        let credentials = if url.username().is_empty() {
+            None
+        } else {
+            let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+            Some((decode(url.username()), decode(url.password().unwrap_or(""))))
+        };
+
+        Ok(ProxyConfig { kind, host, port, credentials })
+    }
+
+    /// 🚇 连接代理并建立到 target 的 TCP 隧道
+    pub async fn tunnel(&self, target_host: &str, target_port: u16) -> OkxResult<TcpStream> {
+        let socket = TcpStream::connect((self.host.as_str(), self.port)).await
+            .map_err(|e| OkxError::Proxy(format!("连接代理 {} 失败: {}", self, e)))?;
+
+        match self.kind {
+            ProxyKind::Http => {
+                let mut stream = socket;
+                let res = match &self.credentials {
+                    Some((user, pass)) => {
+                        http_connect_tokio_with_basic_auth(&mut stream, target_host, target_port, user, pass).await
+                    }
+                    None => http_connect_tokio(&mut stream, target_host, target_port).await,
+                };
+                res.map_err(|e| OkxError::Proxy(format!("CONNECT 握手失败: {}", e)))?;
+                Ok(stream)
+            }
+            ProxyKind::Socks5 { remote_dns: true } => self.socks5_handshake(socket, (target_host, target_port)).await,
+            ProxyKind::Socks5 { remote_dns: false } => {
+                // socks5:// 语义: 先在本地完成 DNS 解析，再把 IP 交给代理
+                let addr = tokio::net::lookup_host((target_host, target_port)).await
+                    .map_err(|e| OkxError::Transport(format!("解析 {} 失败: {}", target_host, e)))?
+                    .next()
+                    .ok_or_else(|| OkxError::Transport(format!("无法解析目标主机 {}", target_host)))?;
+                self.socks5_handshake(socket, addr).await
+            }
+        }
+    }
+
+    async fn socks5_handshake<'t, T>(&self, socket: TcpStream, target: T) -> OkxResult<TcpStream>
+    where
+        T: tokio_socks::IntoTargetAddr<'t>,
+    {
+        let res = match &self.credentials {
+            Some((user, pass)) => Socks5Stream::connect_with_password_and_socket(socket, target, user, pass).await,
+            None => Socks5Stream::connect_with_socket(socket, target).await,
+        };
+        res.map(Socks5Stream::into_inner)
+            .map_err(|e| OkxError::Proxy(format!("SOCKS5 握手失败: {}", e)))
+    }
+}
+
+/// 日志输出时隐藏密码
+impl fmt::Display for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.kind {
+            ProxyKind::Http => "http",
+            ProxyKind::Socks5 { remote_dns: false } => "socks5",
+            ProxyKind::Socks5 { remote_dns: true } => "socks5h",
+        };
+        match &self.credentials {
+            Some((user, _)) => write!(f, "{}://{}:***@{}:{}", scheme, user, self.host, self.port),
+            None => write!(f, "{}://{}:{}", scheme, self.host, self.port),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+// src/okx/rest.rs
+
+use crate::config::AppConfig;
+use crate::okx::client::connect_transport;
+use crate::okx::error::{OkxError, OkxResult};
+use crate::utils::time;
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use url::Url;
+
+/// 📦 OKX REST 统一返回结构: {"code":"0","msg":"","data":[...]}
+#[derive(Debug, Deserialize)]
+struct RestEnvelope<T> {
+    code: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+}
+
+// 响应大小上限 (全量品种列表约 1MB)，防止异常响应撑爆内存
+const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct ServerTime {
+    ts: String,
+}
+
+/// 🌍 [REST 客户端] 极简 HTTP/1.1 GET
+/// 复用 WebSocket 的传输层 (代理 / TLS 校验 / 钉扎)，模拟盘自动附带 x-simulated-trading
+/// 建连与读取各受 REST_TIMEOUT_SECS 限制，超时返回 OkxError::Timeout
+pub struct RestClient {
+    config: AppConfig,
+}
+
+impl RestClient {
+    pub fn new(config: &AppConfig) -> Self {
+        RestClient { config: config.clone() }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> OkxResult<Vec<T>> {
+        Ok(self.get_timed(path_and_query).await?.0)
+    }
+
+    /// 同 get，额外返回 (请求发出, 响应首字节到达) 的本机时间戳
+    /// 计时从传输层 (TCP / 代理 / TLS) 建立之后开始，握手耗时不计入
+    async fn get_timed<T: DeserializeOwned>(&self, path_and_query: &str) -> OkxResult<(Vec<T>, i64, i64)> {
+        let url = Url::parse(&self.config.rest_url)
+            .and_then(|base| base.join(path_and_query))
+            .map_err(|e| OkxError::Config(format!("无效的 REST 地址: {}", e)))?;
+        let host = url.host_str().unwrap_or_default();
+
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: rust_hft_bot\r\nAccept: application/json\r\nConnection: close\r\n",
+            &url[url::Position::BeforePath..], host
+        );
+        for (name, value) in self.config.trading_env().rest_headers() {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+
+        let limit = self.config.rest_timeout;
+        let timed_out = |stage: &str| OkxError::Timeout(format!("GET {} {}超过 {}s", path_and_query, stage, limit.as_secs()));
+
+        let mut stream = tokio::time::timeout(limit, connect_transport(&self.config, &url)).await
+            .map_err(|_| timed_out("建连"))??;
+        let (raw, send_ms, recv_ms) = tokio::time::timeout(limit, async {
+            let send_ms = time::local_timestamp_ms();
+            stream.write_all(request.as_bytes()).await.map_err(|e| OkxError::Transport(e.to_string()))?;
+            let mut first = [0u8; 4096];
+            let n = stream.read(&mut first).await.map_err(|e| OkxError::Transport(e.to_string()))?;
+            let recv_ms = time::local_timestamp_ms();
+            let mut raw = first[..n].to_vec();
+            // 多读 1 字节用于判断是否超限
+            (&mut stream).take((MAX_RESPONSE_BYTES + 1 - n) as u64).read_to_end(&mut raw).await
+                .map_err(|e| OkxError::Transport(e.to_string()))?;
+            if raw.len() > MAX_RESPONSE_BYTES {
+                return Err(OkxError::Transport(format!("GET {} 响应超过 {} 字节", path_and_query, MAX_RESPONSE_BYTES)));
+            }
+            Ok((raw, send_ms, recv_ms))
+        }).await.map_err(|_| timed_out("读取响应"))??;
+
+        let (status, body) = parse_http_response(&raw)?;
+        if status == 429 {
+            return Err(OkxError::RateLimit { code: "429".to_string(), msg: format!("GET {}", path_and_query) });
+        }
+
+        let envelope: RestEnvelope<T> = serde_json::from_slice(&body).map_err(|e| {
+            if (200..300).contains(&status) { OkxError::Protocol(e) } else { OkxError::Transport(format!("HTTP {}", status)) }
+        })?;
+        if envelope.code != "0" {
+            return Err(OkxError::from_api(&envelope.code, &envelope.msg, false));
+        }
+        Ok((envelope.data, send_ms, recv_ms))
+    }
+
+    /// ⏱️ 调用 /api/v5/public/time 做一次校时
+    pub async fn sync_clock(&self) -> OkxResult<time::ClockSample> {
+        let (data, send_ms, recv_ms): (Vec<ServerTime>, _, _) = self.get_timed("/api/v5/public/time").await?;
+
+        let server_ms = data.first()
+            .and_then(|t| t.ts.parse::<i64>().ok())
+            .ok_or_else(|| OkxError::Transport("校时响应缺少 ts".to_string()))?;
+        Ok(time::record_sample(send_ms, server_ms, recv_ms))
+    }
+
+    /// 🔁 后台定时校时任务
+    pub async fn run_clock_sync(self, period: std::time::Duration) {
+        // 启动时已校时一次，从下一个周期开始
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            ticker.tick().await;
+            match self.sync_clock().await {
+                Ok(sample) => {
+                    let drift = time::drift_ppm().map(|d| format!("{:.1}ppm", d)).unwrap_or_else(|| "-".to_string());
+                    info!(
+                        "⏱️ [校时] 样本偏移 {}ms (RTT {}ms) | 生效偏移 {}ms | 漂移 {}",
+                        sample.offset_ms, sample.rtt_ms, time::offset_ms(), drift
+                    );
+                }
+                Err(e) => warn!("⚠️ [校时] 失败，沿用上次偏移 {}ms: {}", time::offset_ms(), e),
+            }
+        }
+    }
+}
+
+/// 解析 HTTP 响应：返回 (状态码, 已解码的 body)
+fn parse_http_response(raw: &[u8]) -> OkxResult<(u16, Vec<u8>)> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut headers);
+    let header_len = match response.parse(raw) {
+        Ok(httparse::Status::Complete(len)) => len,
+        Ok(httparse::Status::Partial) => return Err(OkxError::Transport("HTTP 响应不完整".to_string())),
+        Err(e) => return Err(OkxError::Transport(format!("HTTP 响应解析失败: {}", e))),
+    };
+    let status = response.code.unwrap_or(0);
+    let chunked = response.headers.iter().any(|h| {
+        h.name.eq_ignore_ascii_case("transfer-encoding")
+            && String::from_utf8_lossy(h.value).to_ascii_lowercase().contains("chunked")
+    });
+
+    let body = &raw[header_len..];
+    let body = if chunked { decode_chunked(body)? } else { body.to_vec() };
+    Ok((status, body))
+}
+
+fn decode_chunked(mut body: &[u8]) -> OkxResult<Vec<u8>> {
+    let bad = || OkxError::Transport("chunked 编码格式错误".to_string());
+    let mut out = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n").ok_or_else(bad)?;
+        let size_str = std::str::from_utf8(&body[..line_end]).map_err(|_| bad())?;
+        let size_str = size_str.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_str, 16).map_err(|_| bad())?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(out);
+        }
+        if body.len() < size + 2 {
+            return Err(bad());
+        }
+        out.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_chunked_joins_chunks() {
+        let body = b"4\r\n{\"co\r\nc\r\nde\":\"0\",\"d\":\r\n3\r\n[]}\r\n0\r\n\r\n";
+        assert_eq!(decode_chunked(body).unwrap(), br#"{"code":"0","d":[]}"#);
+    }
+
+    #[test]
+    fn decode_chunked_ignores_extensions_and_hex_case() {
+        let body = b"A;name=value\r\n0123456789\r\n0\r\n\r\n";
+        assert_eq!(decode_chunked(body).unwrap(), b"0123456789");
+    }
+
+    #[test]
+    fn decode_chunked_rejects_truncated_body() {
+        assert!(decode_chunked(b"10\r\nshort\r\n").is_err());
+        assert!(decode_chunked(b"5\r\nhello").is_err());
+        assert!(decode_chunked(b"zz\r\nhello\r\n").is_err());
+    }
+
+    #[test]
+    fn chunked_response_is_decoded() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n";
+        let (status, body) = parse_http_response(raw).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"ok");
+    }
+}
@@ -0,0 +1,383 @@
+// src/okx/subscription.rs
+
+use crate::okx::protocol::{self, WsArg, WsRouter};
+use log::{info, warn, error};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// 单个请求最多携带的参数数量 (OKX 限制单包 64KB，留足余量)
+const MAX_ARGS_PER_PACKET: usize = 50;
+// 超过该时间仍未确认，视为失败
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+// 失败重试: 间隔逐次翻倍，超过次数后放弃
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRIES: u32 = 5;
+
+#[derive(Debug, Clone)]
+pub enum SubState {
+    /// 已发送，等待 event=subscribe 确认
+    Pending { batch_id: String, since: Instant },
+    Confirmed,
+    /// 被拒绝 / 确认超时，到 retry_at 后重发 (原因已记录在日志中)
+    Failed { retry_at: Instant },
+    /// 重试次数用尽，等下一次重连再给机会
+    Abandoned,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    state: SubState,
+    attempts: u32,
+}
+
+/// 📡 [订阅管理器] 每条连接一个
+/// 记录期望订阅的全集，负责批量发包、跟踪确认、失败重试以及重连后重放
+pub struct SubscriptionManager {
+    entries: BTreeMap<WsArg, Entry>,
+    next_id: u64,
+}
+
+impl Default for SubscriptionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubscriptionManager {
+    pub fn new() -> Self {
+        SubscriptionManager { entries: BTreeMap::new(), next_id: 1 }
+    }
+
+    /// ➕ 新增订阅，返回需要发送的数据包 (已订阅 / 等待中的参数会被跳过)
+    pub fn subscribe(&mut self, args: Vec<WsArg>) -> Vec<String> {
+        self.subscribe_at(args, Instant::now())
+    }
+
+    fn subscribe_at(&mut self, args: Vec<WsArg>, now: Instant) -> Vec<String> {
+        let fresh: Vec<WsArg> = args.into_iter()
+            .filter(|a| !matches!(self.entries.get(a).map(|e| &e.state), Some(SubState::Confirmed | SubState::Pending { .. })))
+            .collect();
+        for arg in &fresh {
+            // 批次号由 send_batches 写入
+            let pending = SubState::Pending { batch_id: String::new(), since: now };
+            self.entries.entry(arg.clone()).or_insert(Entry { state: pending, attempts: 0 }).attempts = 0;
+        }
+        self.send_batches(fresh, now)
+    }
+
+    /// ➖ 取消订阅，立即从期望集合中移除 (重连后不再重放)
+    pub fn unsubscribe(&mut self, args: Vec<WsArg>) -> Vec<String> {
+        let removed: Vec<WsArg> = args.into_iter().filter(|a| self.entries.remove(a).is_some()).collect();
+        removed.chunks(MAX_ARGS_PER_PACKET)
+            .map(|chunk| protocol::create_subscription_packet("unsubscribe", &self.take_id(), chunk))
+            .collect()
+    }
+
+    /// 🔁 重连后重放全部期望订阅 (包括之前放弃的，新连接给它们新的机会)
+    pub fn replay(&mut self) -> Vec<String> {
+        self.replay_at(Instant::now())
+    }
+
+    fn replay_at(&mut self, now: Instant) -> Vec<String> {
+        let all: Vec<WsArg> = self.entries.keys().cloned().collect();
+        for entry in self.entries.values_mut() {
+            entry.attempts = 0;
+        }
+        self.send_batches(all, now)
+    }
+
+    /// 📥 处理 subscribe / unsubscribe / error 事件，返回 true 表示该消息已被消费
+    pub fn on_event(&mut self, router: &WsRouter) -> bool {
+        self.on_event_at(router, Instant::now())
+    }
+
+    fn on_event_at(&mut self, router: &WsRouter, now: Instant) -> bool {
+        match router.event.as_deref() {
+            Some("subscribe") => {
+                if let Some(arg) = &router.arg {
+                    if let Some(entry) = self.entries.get_mut(arg) {
+                        entry.state = SubState::Confirmed;
+                        entry.attempts = 0;
+                    }
+                    info!("📡 [订阅] 已确认 {}", arg);
+                }
+                true
+            }
+            Some("unsubscribe") => {
+                if let Some(arg) = &router.arg {
+                    info!("📴 [订阅] 已取消 {}", arg);
+                }
+                true
+            }
+            Some("error") => self.on_error(router, now),
+            _ => false,
+        }
+    }
+
+    /// 错误响应不带 arg，只能通过回显的 id 找到对应批次；
+    /// msg 中提到的参数判为失败，找不到则整批判为失败
+    fn on_error(&mut self, router: &WsRouter, now: Instant) -> bool {
+        let Some(batch_id) = router.id.as_deref() else { return false };
+        let msg = router.msg.clone().unwrap_or_default();
+        let reason = format!("[{}] {}", router.code.as_deref().unwrap_or("-"), msg);
+
+        let in_batch: Vec<WsArg> = self.entries.iter()
+            .filter(|(_, e)| matches!(&e.state, SubState::Pending { batch_id: b, .. } if b == batch_id))
+            .map(|(a, _)| a.clone())
+            .collect();
+        if in_batch.is_empty() {
+            return false;
+        }
+
+        let mentioned: Vec<WsArg> = in_batch.iter()
+            .filter(|a| msg.contains(a.inst_id.as_deref().or(a.ccy.as_deref()).unwrap_or(&a.channel)))
+            .cloned()
+            .collect();
+        let failed = if mentioned.is_empty() { in_batch } else { mentioned };
+        for arg in failed {
+            self.mark_failed(&arg, reason.clone(), now);
+        }
+        true
+    }
+
+    /// ⏰ 定时维护：确认超时的判为失败，到期的失败项重新订阅
+    pub fn due_retries(&mut self) -> Vec<String> {
+        self.due_retries_at(Instant::now())
+    }
+
+    fn due_retries_at(&mut self, now: Instant) -> Vec<String> {
+        let timed_out: Vec<WsArg> = self.entries.iter()
+            .filter(|(_, e)| matches!(e.state, SubState::Pending { since, .. } if now - since > ACK_TIMEOUT))
+            .map(|(a, _)| a.clone())
+            .collect();
+        for arg in timed_out {
+            self.mark_failed(&arg, "订阅确认超时".to_string(), now);
+        }
+
+        let due: Vec<WsArg> = self.entries.iter()
+            .filter(|(_, e)| matches!(e.state, SubState::Failed { retry_at, .. } if now >= retry_at))
+            .map(|(a, _)| a.clone())
+            .collect();
+        if !due.is_empty() {
+            warn!("🔁 [订阅] 重试 {} 个失败订阅", due.len());
+        }
+        self.send_batches(due, now)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 期望订阅的全集 (用于判断连接是否需要 login)
+    pub fn args(&self) -> Vec<WsArg> {
+        self.entries.keys().cloned().collect()
+    }
+
+    fn mark_failed(&mut self, arg: &WsArg, reason: String, now: Instant) {
+        let Some(entry) = self.entries.get_mut(arg) else { return };
+        entry.attempts += 1;
+        if entry.attempts > MAX_RETRIES {
+            error!("⛔ [订阅] {} 连续失败 {} 次，放弃: {}", arg, MAX_RETRIES, reason);
+            entry.state = SubState::Abandoned;
+        } else {
+            let delay = RETRY_BASE_DELAY * 2u32.pow(entry.attempts - 1);
+            warn!("⚠️ [订阅] {} 失败 (第 {} 次)，{}s 后重试: {}", arg, entry.attempts, delay.as_secs(), reason);
+            entry.state = SubState::Failed { retry_at: now + delay };
+        }
+    }
+
+    fn send_batches(&mut self, args: Vec<WsArg>, now: Instant) -> Vec<String> {
+        let mut packets = Vec::new();
+        for chunk in args.chunks(MAX_ARGS_PER_PACKET) {
+            let batch_id = self.take_id();
+            for arg in chunk {
+                if let Some(entry) = self.entries.get_mut(arg) {
+                    entry.state = SubState::Pending { batch_id: batch_id.clone(), since: now };
+                }
+            }
+            packets.push(protocol::create_subscription_packet("subscribe", &batch_id, chunk));
+        }
+        packets
+    }
+
+    fn take_id(&mut self) -> String {
+        let id = format!("sub{}", self.next_id);
+        self.next_id += 1;
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::okx::protocol::ChannelType;
+
+    fn tickers(n: usize) -> Vec<WsArg> {
+        (0..n).map(|i| WsArg::for_inst(ChannelType::Tickers, &format!("COIN{}-USDT", i))).collect()
+    }
+
+    /// 数据包 -> (id, args)
+    fn unpack(packet: &str) -> (String, Vec<WsArg>) {
+        let v: serde_json::Value = serde_json::from_str(packet).unwrap();
+        assert_eq!(v["op"], "subscribe");
+        (v["id"].as_str().unwrap().to_string(), serde_json::from_value(v["args"].clone()).unwrap())
+    }
+
+    fn ack(arg: &WsArg) -> WsRouter {
+        WsRouter::parse(&serde_json::json!({ "event": "subscribe", "arg": arg }).to_string()).unwrap()
+    }
+
+    fn error(id: &str, msg: &str) -> WsRouter {
+        WsRouter::parse(&serde_json::json!({ "event": "error", "code": "60018", "msg": msg, "id": id }).to_string()).unwrap()
+    }
+
+    fn state(m: &SubscriptionManager, arg: &WsArg) -> SubState {
+        m.entries[arg].state.clone()
+    }
+
+    fn retry_at(m: &SubscriptionManager, arg: &WsArg) -> Instant {
+        match state(m, arg) {
+            SubState::Failed { retry_at } => retry_at,
+            other => panic!("{} 应为 Failed，实际 {:?}", arg, other),
+        }
+    }
+
+    #[test]
+    fn subscribe_splits_into_batches_of_fifty() {
+        let mut m = SubscriptionManager::new();
+        let packets = m.subscribe_at(tickers(120), Instant::now());
+        let batches: Vec<_> = packets.iter().map(|p| unpack(p)).collect();
+        assert_eq!(batches.iter().map(|(_, a)| a.len()).collect::<Vec<_>>(), [50, 50, 20]);
+        assert_eq!(batches.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(), ["sub1", "sub2", "sub3"]);
+
+        // 每个参数记录自己所在的批次
+        for (id, args) in &batches {
+            for arg in args {
+                assert!(matches!(state(&m, arg), SubState::Pending { batch_id, .. } if &batch_id == id));
+            }
+        }
+        assert_eq!(m.len(), 120);
+    }
+
+    #[test]
+    fn pending_and_confirmed_args_are_not_resent() {
+        let mut m = SubscriptionManager::new();
+        let now = Instant::now();
+        let args = tickers(2);
+        m.subscribe_at(args.clone(), now);
+        assert!(m.subscribe_at(args.clone(), now).is_empty());
+
+        assert!(m.on_event_at(&ack(&args[0]), now));
+        assert!(matches!(state(&m, &args[0]), SubState::Confirmed));
+
+        // 只有新参数会发出
+        let mut more = args.clone();
+        more.extend(tickers(3).into_iter().skip(2));
+        let packets = m.subscribe_at(more, now);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(unpack(&packets[0]).1, tickers(3)[2..]);
+    }
+
+    #[test]
+    fn ack_timeout_marks_failed_then_retries() {
+        let mut m = SubscriptionManager::new();
+        let t0 = Instant::now();
+        let arg = tickers(1).remove(0);
+        m.subscribe_at(vec![arg.clone()], t0);
+
+        assert!(m.due_retries_at(t0 + ACK_TIMEOUT).is_empty());
+        assert!(matches!(state(&m, &arg), SubState::Pending { .. }));
+
+        // 超时后判为失败，还不到重试时间
+        let t1 = t0 + ACK_TIMEOUT + Duration::from_millis(1);
+        assert!(m.due_retries_at(t1).is_empty());
+        assert_eq!(retry_at(&m, &arg), t1 + RETRY_BASE_DELAY);
+
+        // 到点重发，重新进入等待
+        let packets = m.due_retries_at(t1 + RETRY_BASE_DELAY);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(unpack(&packets[0]).1, std::slice::from_ref(&arg));
+        assert!(matches!(state(&m, &arg), SubState::Pending { since, .. } if since == t1 + RETRY_BASE_DELAY));
+    }
+
+    #[test]
+    fn retries_back_off_then_abandon() {
+        let mut m = SubscriptionManager::new();
+        let mut now = Instant::now();
+        let arg = tickers(1).remove(0);
+        let mut packets = m.subscribe_at(vec![arg.clone()], now);
+
+        for attempt in 1..=MAX_RETRIES {
+            let (id, _) = unpack(&packets[0]);
+            assert!(m.on_event_at(&error(&id, "Invalid request"), now));
+            let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+            assert_eq!(retry_at(&m, &arg), now + delay);
+
+            // 间隔之前不重发
+            assert!(m.due_retries_at(now + delay - Duration::from_millis(1)).is_empty());
+            now += delay;
+            packets = m.due_retries_at(now);
+            assert_eq!(packets.len(), 1);
+        }
+        assert_eq!(RETRY_BASE_DELAY * 2u32.pow(MAX_RETRIES - 1), Duration::from_secs(32));
+
+        // 第 6 次失败: 放弃，之后不再重试
+        let (id, _) = unpack(&packets[0]);
+        m.on_event_at(&error(&id, "Invalid request"), now);
+        assert!(matches!(state(&m, &arg), SubState::Abandoned));
+        assert!(m.due_retries_at(now + Duration::from_secs(3600)).is_empty());
+    }
+
+    #[test]
+    fn error_fails_mentioned_args_or_whole_batch() {
+        let mut m = SubscriptionManager::new();
+        let now = Instant::now();
+        let args = tickers(3);
+        let (id, _) = unpack(&m.subscribe_at(args.clone(), now)[0]);
+
+        // 未知批次不消费
+        assert!(!m.on_event_at(&error("sub99", "COIN1-USDT doesn't exist"), now));
+
+        // msg 点名的参数失败，其余仍在等待
+        assert!(m.on_event_at(&error(&id, "Wrong URL or channel:tickers,instId:COIN1-USDT doesn't exist"), now));
+        assert!(matches!(state(&m, &args[0]), SubState::Pending { .. }));
+        assert!(matches!(state(&m, &args[1]), SubState::Failed { .. }));
+        assert!(matches!(state(&m, &args[2]), SubState::Pending { .. }));
+
+        // 没有点名: 整批 (仍在等待的) 失败
+        assert!(m.on_event_at(&error(&id, "Illegal request"), now));
+        assert!(args.iter().all(|a| matches!(state(&m, a), SubState::Failed { .. })));
+    }
+
+    #[test]
+    fn replay_resends_everything_and_resets_attempts() {
+        let mut m = SubscriptionManager::new();
+        let mut now = Instant::now();
+        let args = tickers(3);
+        m.subscribe_at(args.clone(), now);
+        m.on_event_at(&ack(&args[0]), now);
+
+        // args[1] 失败到放弃
+        for _ in 0..=MAX_RETRIES {
+            now += ACK_TIMEOUT * 10;
+            m.entries.get_mut(&args[1]).unwrap().state = SubState::Pending { batch_id: "x".into(), since: now - ACK_TIMEOUT * 2 };
+            m.due_retries_at(now);
+        }
+        assert!(matches!(state(&m, &args[1]), SubState::Abandoned));
+
+        // 取消订阅的不再重放
+        let removed = m.unsubscribe(vec![args[2].clone()]);
+        assert_eq!(removed.len(), 1);
+
+        let packets = m.replay_at(now);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(unpack(&packets[0]).1, args[..2]);
+        assert!(args[..2].iter().all(|a| matches!(state(&m, a), SubState::Pending { .. })));
+
+        // 重放后重新从第 1 次计算退避
+        let (id, _) = unpack(&packets[0]);
+        m.on_event_at(&error(&id, "Illegal request"), now);
+        assert_eq!(retry_at(&m, &args[1]), now + RETRY_BASE_DELAY);
+    }
+}
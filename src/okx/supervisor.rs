@@ -0,0 +1,330 @@
+// src/okx/supervisor.rs
+
+use crate::config::AppConfig;
+use crate::okx::client::{OkxClient, WsReadStream, WsStream, WsWriteStream};
+use crate::okx::correlation::{RequestTracker, ResponseHandle};
+use crate::okx::error::{OkxError, OkxResult};
+use crate::okx::protocol::{Endpoint, OpPacket, WsArg, WsRouter};
+use crate::okx::subscription::SubscriptionManager;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn, error};
+use rand::Rng;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// 订阅管理器在守护者 (重连重放) 与活动连接 (收确认 / 重试) 之间共享
+pub type SharedSubscriptions = Arc<Mutex<SubscriptionManager>>;
+
+// ⚙️ 重连参数
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// ⏳ 指数退避 + 随机抖动
+/// 等待时间 = min(max, base * 2^n) * random(0.5 ~ 1.0)，避免多个连接同时重连撞墙
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Backoff { base, max, attempt: 0 }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let exp = self.base.saturating_mul(1u32 << self.attempt.min(16));
+        let capped = exp.min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        let jitter = rand::thread_rng().gen_range(0.5..=1.0);
+        capped.mul_f64(jitter)
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// 🛡️ [连接守护] 包装 OkxClient
+/// 负责: 断线重连 (带退避) -> 私有频道重新 login -> 重放全部订阅
+pub struct ConnectionSupervisor {
+    client: OkxClient,
+    subscriptions: SharedSubscriptions,
+    backoff: Backoff,
+}
+
+impl ConnectionSupervisor {
+    pub fn new(endpoint: Endpoint) -> Self {
+        ConnectionSupervisor {
+            client: OkxClient::new(endpoint),
+            subscriptions: Arc::new(Mutex::new(SubscriptionManager::new())),
+            backoff: Backoff::new(RECONNECT_BASE_DELAY, RECONNECT_MAX_DELAY),
+        }
+    }
+
+    /// 📝 登记订阅 (连接建立前调用)，每次连接成功后都会自动重放
+    /// 连接建立后的增减请使用 Connection::subscribe / unsubscribe
+    pub fn subscribe(&self, args: Vec<WsArg>) {
+        self.subscriptions.lock().unwrap().subscribe(args);
+    }
+
+    /// 🔁 阻塞直到拿到一条可用连接
+    /// 私有频道的 login 在 OkxClient::connect 内部完成，这里只负责重试与订阅重放
+    /// 只有致命错误 (配置错误 / 鉴权被拒) 才会返回 Err，其余错误一律退避重试
+    pub async fn connect(&mut self, config: &AppConfig) -> OkxResult<Connection> {
+        let endpoint = self.client.endpoint();
+
+        loop {
+            let login = endpoint.requires_login(&self.subscriptions.lock().unwrap().args());
+            let result = match self.client.connect(config, login).await {
+                Ok(mut ws) => self.replay_subscriptions(&mut ws).await.map(|n| (ws, n)),
+                Err(e) => Err(e),
+            };
+
+            let delay = match result {
+                Ok((ws, batches)) => {
+                    info!(
+                        "✅ [{:?}] 连接就绪，已重放 {} 个订阅 ({} 个请求) | connId: {}",
+                        endpoint, self.subscriptions.lock().unwrap().len(), batches, self.client.conn_id().unwrap_or("-")
+                    );
+                    self.backoff.reset();
+                    let (write, read) = ws.split();
+                    return Ok(Connection { requests: RequestTracker::new(), write, read, subscriptions: self.subscriptions.clone() });
+                }
+                Err(e) if e.is_fatal() => {
+                    error!("⛔ [{:?}] 致命错误，停止重连: {}", endpoint, e);
+                    return Err(e);
+                }
+                Err(e @ OkxError::RateLimit { .. }) => {
+                    // 限频时直接按最大间隔等待，避免越重试越被封
+                    error!("🐢 [{:?}] {}", endpoint, e);
+                    self.backoff.next_delay();
+                    RECONNECT_MAX_DELAY
+                }
+                Err(e) => {
+                    error!("❌ [{:?}] {}", endpoint, e);
+                    self.backoff.next_delay()
+                }
+            };
+
+            warn!("🔁 [{:?}] 第 {} 次连接失败，{:.1}s 后重试", endpoint, self.backoff.attempt(), delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn replay_subscriptions(&self, ws: &mut WsStream) -> OkxResult<usize> {
+        let packets = self.subscriptions.lock().unwrap().replay();
+        for packet in &packets {
+            ws.send(Message::Text(packet.clone())).await?;
+        }
+        Ok(packets.len())
+    }
+}
+
+/// 🔗 [活动连接] 读写两半 + 共享的订阅状态 + 交易请求关联 (随连接新建，断线即作废)
+pub struct Connection {
+    requests: RequestTracker,
+    pub write: WsWriteStream,
+    pub read: WsReadStream,
+    subscriptions: SharedSubscriptions,
+}
+
+impl Connection {
+    pub async fn send(&mut self, text: String) -> Result<(), tungstenite::Error> {
+        self.write.send(Message::Text(text)).await
+    }
+
+    pub async fn subscribe(&mut self, args: Vec<WsArg>) -> Result<(), tungstenite::Error> {
+        let packets = self.subscriptions.lock().unwrap().subscribe(args);
+        self.send_all(packets).await
+    }
+
+    pub async fn unsubscribe(&mut self, args: Vec<WsArg>) -> Result<(), tungstenite::Error> {
+        let packets = self.subscriptions.lock().unwrap().unsubscribe(args);
+        self.send_all(packets).await
+    }
+
+    /// 📤 发送交易请求并登记，返回可等待回执的句柄
+    pub async fn request(&mut self, packet: &OpPacket) -> Result<ResponseHandle, tungstenite::Error> {
+        let handle = self.requests.register(packet);
+        self.send(packet.payload.clone()).await?;
+        Ok(handle)
+    }
+
+    /// 📥 订阅确认 / 错误事件交给订阅管理器，返回 true 表示已消费
+    /// 交易请求回执在这里配对后交给等待方 (ResponseHandle)，同样算已消费
+    pub fn on_event(&mut self, router: &WsRouter) -> bool {
+        if router.op.is_some() {
+            self.requests.resolve(router);
+            return true;
+        }
+        self.subscriptions.lock().unwrap().on_event(router)
+    }
+
+    /// ⏰ 定时调用：重发到期的失败订阅，清理无回执的请求
+    pub async fn maintain(&mut self) -> Result<(), tungstenite::Error> {
+        self.requests.expire();
+        let packets = self.subscriptions.lock().unwrap().due_retries();
+        self.send_all(packets).await
+    }
+
+    async fn send_all(&mut self, packets: Vec<String>) -> Result<(), tungstenite::Error> {
+        for packet in packets {
+            self.send(packet).await?;
+        }
+        Ok(())
+    }
+}
+
+// ==========================================
+// 🔗 连接槽位 (后台重连)
+// ==========================================
+
+type ReconnectTask = JoinHandle<(ConnectionSupervisor, OkxResult<Connection>)>;
+
+/// 📨 连接槽位上的事件
+pub enum LinkEvent {
+    /// 在线连接读到的一帧
+    Frame(Option<Result<Message, tungstenite::Error>>),
+    /// 后台重连完成，新连接已就位 (订阅已重放)
+    Reconnected,
+    /// 重连遇到致命错误 (配置错误 / 鉴权被拒)，重试无意义
+    Fatal(OkxError),
+}
+
+/// 🔗 [连接槽位] 持有一路连接及其守护者
+/// 断线后守护者被移交给后台任务重连，事件循环照常服务其他连接 (读消息 / 心跳 / 下单)，
+/// 重连结果由 recv 取回
+pub struct Link {
+    endpoint: Endpoint,
+    config: AppConfig,
+    conn: Option<Connection>,
+    sup: Option<ConnectionSupervisor>,
+    reconnecting: Option<ReconnectTask>,
+}
+
+impl Link {
+    /// 启动时建立首条连接 (阻塞直到成功或遇到致命错误)
+    pub async fn open(mut sup: ConnectionSupervisor, config: &AppConfig) -> OkxResult<Self> {
+        let conn = sup.connect(config).await?;
+        Ok(Link { endpoint: sup.client.endpoint(), config: config.clone(), conn: Some(conn), sup: Some(sup), reconnecting: None })
+    }
+
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// 在线时返回当前连接，重连期间返回 None
+    pub fn conn(&mut self) -> Option<&mut Connection> {
+        self.conn.as_mut()
+    }
+
+    /// 🔁 丢弃当前连接，在后台任务里重连 (已在重连中则忽略)
+    pub fn reconnect(&mut self) {
+        self.conn = None;
+        let Some(mut sup) = self.sup.take() else { return };
+        let config = self.config.clone();
+        self.reconnecting = Some(tokio::spawn(async move {
+            let result = sup.connect(&config).await;
+            (sup, result)
+        }));
+    }
+
+    /// 📥 下一个事件：在线时读一帧，重连中等待重连结果
+    /// 可安全地放在 select! 里 (被取消时不会丢帧，也不会中断后台重连)
+    pub async fn recv(&mut self) -> LinkEvent {
+        if let Some(task) = self.reconnecting.as_mut() {
+            let joined = task.await;
+            self.reconnecting = None;
+            return match joined {
+                Ok((sup, Ok(conn))) => {
+                    self.sup = Some(sup);
+                    self.conn = Some(conn);
+                    LinkEvent::Reconnected
+                }
+                Ok((_, Err(e))) => LinkEvent::Fatal(e),
+                Err(e) => LinkEvent::Fatal(OkxError::Transport(format!("[{:?}] 重连任务异常退出: {}", self.endpoint, e))),
+            };
+        }
+        match self.conn.as_mut() {
+            Some(conn) => LinkEvent::Frame(conn.read.next().await),
+            None => std::future::pending().await,
+        }
+    }
+}
+
+// ==========================================
+// 🔌 断线原因
+// ==========================================
+
+#[derive(Debug, Clone)]
+pub enum DisconnectReason {
+    /// 服务器发来 Close 帧 (如 30s 空闲断开 / 维护)
+    Closed { code: Option<u16>, reason: String },
+    /// 流结束 (对端直接断开 TCP)
+    StreamEnded,
+    /// 读写出错
+    Error(String),
+    /// 看门狗判定连接已失活
+    Watchdog(String),
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectReason::Closed { code: Some(code), reason } => write!(f, "服务器关闭连接 (code={}, reason={})", code, reason),
+            DisconnectReason::Closed { code: None, .. } => write!(f, "服务器关闭连接 (无关闭码)"),
+            DisconnectReason::StreamEnded => write!(f, "连接流已结束"),
+            DisconnectReason::Error(e) => write!(f, "连接异常: {}", e),
+            DisconnectReason::Watchdog(e) => write!(f, "看门狗判定失活: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Disconnect {
+    pub endpoint: Endpoint,
+    pub reason: DisconnectReason,
+}
+
+/// 📨 读取结果分类：文本消息 / 可忽略的控制帧 / 连接已丢失
+pub enum Inbound {
+    Text(String),
+    Control,
+    Lost(DisconnectReason),
+}
+
+impl Inbound {
+    pub fn from_frame(endpoint: Endpoint, frame: Option<Result<Message, tokio_tungstenite::tungstenite::Error>>) -> Self {
+        match frame {
+            Some(Ok(Message::Text(text))) => Inbound::Text(text),
+            Some(Ok(Message::Close(frame))) => {
+                let (code, reason) = match frame {
+                    Some(f) => (Some(u16::from(f.code)), f.reason.into_owned()),
+                    None => (None, String::new()),
+                };
+                Inbound::Lost(DisconnectReason::Closed { code, reason })
+            }
+            Some(Ok(Message::Binary(data))) => {
+                // OKX V5 不会推送二进制帧，出现即说明协议异常，记录后忽略
+                warn!("⚠️ [{:?}] 收到意外的二进制帧 ({} bytes)，已忽略", endpoint, data.len());
+                Inbound::Control
+            }
+            // Ping 由 tungstenite 自动回复 Pong；我们使用文本 "ping"，协议层 Pong 无需处理
+            Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) | Some(Ok(Message::Frame(_))) => Inbound::Control,
+            Some(Err(e)) => Inbound::Lost(DisconnectReason::Error(e.to_string())),
+            None => Inbound::Lost(DisconnectReason::StreamEnded),
+        }
+    }
+}
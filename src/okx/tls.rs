@@ -0,0 +1,104 @@
+// src/okx/tls.rs
+
+use crate::okx::error::{OkxError, OkxResult};
+use base64::{Engine as _, engine::general_purpose};
+use log::warn;
+use native_tls::{Certificate, TlsConnector};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_native_tls::TlsStream;
+
+/// 🔐 [TLS 配置]
+/// 默认始终校验证书；可追加自定义 CA (公司 MITM 代理)、对 OKX 证书做 SPKI 钉扎，
+/// 跳过校验只能通过 TLS_INSECURE_SKIP_VERIFY 显式开启
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// 额外信任的 CA 证书 (PEM，每个元素一张)
+    extra_roots: Vec<Vec<u8>>,
+    /// sha256(SubjectPublicKeyInfo) 列表，任一匹配即通过 (便于证书轮换)
+    pinned_spki: Vec<[u8; 32]>,
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsOptions {
+    pub fn new(ca_bundle_path: Option<&str>, pins: Option<&str>, insecure_skip_verify: bool) -> Result<Self, String> {
+        let extra_roots = match ca_bundle_path {
+            Some(path) => {
+                let pem = std::fs::read(path).map_err(|e| format!("读取 CA 文件 {} 失败: {}", path, e))?;
+                let roots = split_pem_certificates(&pem);
+                if roots.is_empty() {
+                    return Err(format!("CA 文件 {} 中没有找到 PEM 证书", path));
+                }
+                for cert in &roots {
+                    Certificate::from_pem(cert).map_err(|e| format!("CA 证书解析失败 ({}): {}", path, e))?;
+                }
+                roots
+            }
+            None => Vec::new(),
+        };
+
+        let mut pinned_spki = Vec::new();
+        for pin in pins.unwrap_or("").split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let pin = pin.strip_prefix("sha256/").unwrap_or(pin);
+            let raw = general_purpose::STANDARD.decode(pin).map_err(|e| format!("SPKI 指纹 {} 不是合法的 Base64: {}", pin, e))?;
+            let digest: [u8; 32] = raw.try_into().map_err(|_| format!("SPKI 指纹 {} 长度错误，应为 SHA-256", pin))?;
+            pinned_spki.push(digest);
+        }
+
+        Ok(TlsOptions { extra_roots, pinned_spki, insecure_skip_verify })
+    }
+
+    pub fn extra_root_count(&self) -> usize {
+        self.extra_roots.len()
+    }
+
+    pub fn pin_count(&self) -> usize {
+        self.pinned_spki.len()
+    }
+
+    pub fn connector(&self) -> OkxResult<TlsConnector> {
+        let mut builder = TlsConnector::builder();
+        for pem in &self.extra_roots {
+            let cert = Certificate::from_pem(pem).map_err(|e| OkxError::Tls(e.to_string()))?;
+            builder.add_root_certificate(cert);
+        }
+        if self.insecure_skip_verify {
+            warn!("☠️ [TLS] 证书校验已关闭 (TLS_INSECURE_SKIP_VERIFY=true)，连接可被中间人劫持!");
+            builder.danger_accept_invalid_certs(true);
+        }
+        builder.build().map_err(|e| OkxError::Tls(format!("初始化失败: {}", e)))
+    }
+
+    /// 📌 校验服务端叶子证书的 SPKI 指纹 (未配置钉扎时直接通过)
+    pub fn verify_pin(&self, stream: &TlsStream<TcpStream>) -> OkxResult<()> {
+        if self.pinned_spki.is_empty() {
+            return Ok(());
+        }
+
+        let cert = stream.get_ref().peer_certificate()
+            .map_err(|e| OkxError::Tls(format!("读取服务端证书失败: {}", e)))?
+            .ok_or_else(|| OkxError::Tls("服务端未提供证书".to_string()))?;
+        let der = cert.to_der().map_err(|e| OkxError::Tls(format!("证书编码失败: {}", e)))?;
+        let (_, x509) = x509_parser::parse_x509_certificate(&der)
+            .map_err(|e| OkxError::Tls(format!("证书解析失败: {}", e)))?;
+
+        let actual: [u8; 32] = Sha256::digest(x509.tbs_certificate.subject_pki.raw).into();
+        if self.pinned_spki.contains(&actual) {
+            Ok(())
+        } else {
+            Err(OkxError::Tls(format!("SPKI 指纹不匹配，服务端为 sha256/{}", general_purpose::STANDARD.encode(actual))))
+        }
+    }
+}
+
+/// 把 CA Bundle 拆成单张 PEM (native-tls 的 from_pem 只读取第一张)
+fn split_pem_certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let text = String::from_utf8_lossy(pem);
+
+    text.split_inclusive(END)
+        .filter_map(|chunk| chunk.find("-----BEGIN CERTIFICATE-----").map(|start| &chunk[start..]))
+        .filter(|block| block.ends_with(END))
+        .map(|block| block.as_bytes().to_vec())
+        .collect()
+}
@@ -1,5 +1,7 @@
+use crate::okx::protocol::Side;
 use serde::{Deserialize, Serialize};
 
+/// 📑 orders 频道推送 (成交相关字段在未成交时为空字符串)
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[allow(dead_code)] // 暂时抑制警告
 pub struct Order {
@@ -11,10 +13,43 @@ pub struct Order {
     pub client_oid: String,
     pub px: String,
     pub sz: String,
-    pub side: String,
+    pub side: Side,
     pub state: String,
     #[serde(rename = "cTime")]
     pub create_time: String,
+
+    // 累计成交
+    #[serde(rename = "accFillSz", default)]
+    pub acc_fill_sz: String,
+    #[serde(rename = "avgPx", default)]
+    pub avg_px: String,
+
+    // 最新一笔成交
+    #[serde(rename = "tradeId", default)]
+    pub trade_id: String,
+    #[serde(rename = "fillPx", default)]
+    pub fill_px: String,
+    #[serde(rename = "fillSz", default)]
+    pub fill_sz: String,
+    #[serde(rename = "fillFee", default)]
+    pub fill_fee: String,
+    #[serde(rename = "fillFeeCcy", default)]
+    pub fill_fee_ccy: String,
+    #[serde(rename = "uTime", default)]
+    pub update_time: String,
+}
+
+/// 📨 order 等交易请求的逐笔回执 (sCode 非 0 表示该笔被拒)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OrderAck {
+    #[serde(rename = "clOrdId", default)]
+    pub client_oid: String,
+    #[serde(rename = "ordId", default)]
+    pub ord_id: String,
+    #[serde(rename = "sCode")]
+    pub s_code: String,
+    #[serde(rename = "sMsg", default)]
+    pub s_msg: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
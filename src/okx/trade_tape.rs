@@ -0,0 +1,132 @@
+// src/okx/trade_tape.rs
+
+use crate::okx::market_data::Trade;
+use crate::okx::protocol::Side;
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+// 每个品种只保留最近 60s 的成交
+const RETENTION_MS: i64 = 60_000;
+// 统计中保留的最大单笔数量
+const TOP_PRINTS: usize = 3;
+
+/// 单笔成交 (已解析为数值)
+#[derive(Debug, Clone, Copy)]
+pub struct Print {
+    pub ts: i64,
+    pub px: f64,
+    pub sz: f64,
+    pub side: Side,
+}
+
+impl Print {
+    pub fn notional(&self) -> f64 {
+        self.px * self.sz
+    }
+}
+
+/// 📊 某个时间窗口内的成交流统计 (量均为基础币，金额为计价币)
+#[derive(Debug, Clone, Default)]
+pub struct TapeStats {
+    pub window_ms: i64,
+    /// 实际成交笔数 (聚合推送按 count 展开)
+    pub trades: u64,
+    pub buy_qty: f64,
+    pub sell_qty: f64,
+    pub buy_notional: f64,
+    pub sell_notional: f64,
+    pub vwap: f64,
+    /// 金额最大的几笔，从大到小
+    pub largest: Vec<Print>,
+}
+
+impl TapeStats {
+    /// 每秒成交笔数
+    pub fn trade_rate(&self) -> f64 {
+        if self.window_ms <= 0 { 0.0 } else { self.trades as f64 * 1000.0 / self.window_ms as f64 }
+    }
+
+    /// 主动卖出金额占比 (0~1)
+    pub fn sell_ratio(&self) -> f64 {
+        let total = self.buy_notional + self.sell_notional;
+        if total <= 0.0 { 0.0 } else { self.sell_notional / total }
+    }
+}
+
+/// 💱 [成交流] trades 推送 / 历史回补按品种滚动保存，供策略查询任意窗口的统计
+pub struct TradeTape {
+    prints: RwLock<HashMap<String, VecDeque<(Print, u64)>>>,
+    // 每个品种已收录的最大 tradeId (回补与实时推送重叠时去重)
+    last_trade_id: RwLock<HashMap<String, u64>>,
+}
+
+impl Default for TradeTape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradeTape {
+    pub fn new() -> Self {
+        TradeTape { prints: RwLock::new(HashMap::new()), last_trade_id: RwLock::new(HashMap::new()) }
+    }
+
+    /// 📥 成交推送 / 回补：按 tradeId 去重后追加，淘汰超出保留时长的旧成交
+    /// 返回本次新收录的成交 (供 K 线合成等下游使用，避免重复计量)
+    pub fn on_trades(&self, trades: Vec<Trade>) -> Vec<Trade> {
+        let mut prints = self.prints.write().unwrap();
+        let mut last_ids = self.last_trade_id.write().unwrap();
+        let mut fresh = Vec::with_capacity(trades.len());
+        for t in trades {
+            let Ok(ts) = t.ts.parse::<i64>() else { continue };
+            if let Ok(id) = t.trade_id.parse::<u64>() {
+                let last = last_ids.entry(t.inst_id.clone()).or_insert(0);
+                if id <= *last { continue; }
+                *last = id;
+            }
+            let queue = prints.entry(t.inst_id.clone()).or_default();
+            queue.push_back((Print { ts, px: t.px, sz: t.sz, side: t.side }, t.trade_count()));
+            let newest = queue.back().map(|(p, _)| p.ts).unwrap_or(ts);
+            while queue.front().is_some_and(|(p, _)| newest - p.ts > RETENTION_MS) {
+                queue.pop_front();
+            }
+            fresh.push(t);
+        }
+        fresh
+    }
+
+    /// 🔍 [now - window_ms, now] 内的统计 (没有任何成交记录的品种返回 None)
+    pub fn stats(&self, inst_id: &str, window_ms: i64, now: i64) -> Option<TapeStats> {
+        let prints = self.prints.read().unwrap();
+        let queue = prints.get(inst_id)?;
+        let mut stats = TapeStats { window_ms, ..Default::default() };
+        let mut qty = 0.0;
+        for (print, count) in queue.iter().rev().take_while(|(p, _)| now - p.ts <= window_ms) {
+            stats.trades += count;
+            qty += print.sz;
+            match print.side {
+                Side::Buy => {
+                    stats.buy_qty += print.sz;
+                    stats.buy_notional += print.notional();
+                }
+                Side::Sell => {
+                    stats.sell_qty += print.sz;
+                    stats.sell_notional += print.notional();
+                }
+            }
+            stats.largest.push(*print);
+        }
+        if qty > 0.0 {
+            stats.vwap = (stats.buy_notional + stats.sell_notional) / qty;
+        }
+        stats.largest.sort_by(|a, b| b.notional().total_cmp(&a.notional()));
+        stats.largest.truncate(TOP_PRINTS);
+        Some(stats)
+    }
+
+    /// 断线后丢弃 (缺口期间的成交无法补齐)
+    pub fn clear(&self) {
+        self.prints.write().unwrap().clear();
+        self.last_trade_id.write().unwrap().clear();
+    }
+}
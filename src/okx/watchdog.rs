@@ -0,0 +1,127 @@
+// src/okx/watchdog.rs
+
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// ⚙️ 存活检测参数 (来自 AppConfig)
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// 心跳间隔 (OKX 30s 无数据会主动断开，必须小于 30s)
+    pub ping_interval: Duration,
+    /// 发出 ping 后多久收不到 pong 视为连接已死
+    pub pong_timeout: Duration,
+    /// 静默多久暂停开新仓
+    pub silence_pause: Duration,
+    /// 静默多久强制重连
+    pub silence_reconnect: Duration,
+    /// 单个品种多久没有推送视为失联
+    pub instrument_stale: Duration,
+}
+
+/// 🩺 连接健康度 (逐级升级)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    /// 暂停开新仓，继续观察
+    Degraded(String),
+    /// 必须重连
+    Dead(String),
+}
+
+/// 🐕 [静默看门狗] 每条连接一个
+/// 记录最后一条数据 / 最后一次 pong / 每个品种最后一次推送的时间
+/// 注意: pong 只证明 TCP 还活着，不代表行情还在推，所以不计入"最后一条数据"
+pub struct ConnectionWatchdog {
+    cfg: WatchdogConfig,
+    // 行情类连接才检测静默 (账户频道只在有变动时推送，静默是常态)
+    data_feed: bool,
+    last_message: Instant,
+    last_pong: Instant,
+    // 已发出但尚未收到 pong 的 ping
+    pending_ping: Option<Instant>,
+    instruments: HashMap<String, Instant>,
+    // 已告警过的失联品种 (避免每秒刷屏)
+    stale_reported: HashSet<String>,
+}
+
+impl ConnectionWatchdog {
+    pub fn new(cfg: WatchdogConfig, data_feed: bool) -> Self {
+        let now = Instant::now();
+        ConnectionWatchdog {
+            cfg,
+            data_feed,
+            last_message: now,
+            last_pong: now,
+            pending_ping: None,
+            instruments: HashMap::new(),
+            stale_reported: HashSet::new(),
+        }
+    }
+
+    pub fn on_message(&mut self) {
+        self.last_message = Instant::now();
+    }
+
+    pub fn on_ping(&mut self) {
+        // 上一个 ping 还没回时不覆盖，超时从最早那次算起
+        self.pending_ping.get_or_insert_with(Instant::now);
+    }
+
+    pub fn on_pong(&mut self) {
+        self.last_pong = Instant::now();
+        self.pending_ping = None;
+    }
+
+    /// 首次推送时自动登记该品种
+    pub fn on_instrument_update(&mut self, inst_id: &str) {
+        self.instruments.insert(inst_id.to_string(), Instant::now());
+        if self.stale_reported.remove(inst_id) {
+            warn!("🐕 [看门狗] {} 恢复推送", inst_id);
+        }
+    }
+
+    /// 🩺 综合评估连接健康度
+    pub fn check(&mut self) -> Health {
+        let now = Instant::now();
+
+        if let Some(sent) = self.pending_ping {
+            let waited = now - sent;
+            if waited > self.cfg.pong_timeout {
+                return Health::Dead(format!(
+                    "{:.1}s 未收到 pong (上次 pong 在 {:.0}s 前)",
+                    waited.as_secs_f64(), (now - self.last_pong).as_secs_f64()
+                ));
+            }
+        }
+
+        if !self.data_feed {
+            return Health::Healthy;
+        }
+
+        let silent = now - self.last_message;
+        if silent > self.cfg.silence_reconnect {
+            return Health::Dead(format!("已静默 {:.1}s", silent.as_secs_f64()));
+        }
+
+        // 单品种失联：逐个告警；全部失联说明订阅已失效，需重连重新订阅
+        let mut stale = 0;
+        for (inst_id, ts) in &self.instruments {
+            if now - *ts > self.cfg.instrument_stale {
+                stale += 1;
+                if self.stale_reported.insert(inst_id.clone()) {
+                    warn!("🐕 [看门狗] {} 已 {:.0}s 无推送", inst_id, (now - *ts).as_secs_f64());
+                }
+            }
+        }
+        if stale > 0 && stale == self.instruments.len() {
+            return Health::Dead(format!("全部 {} 个品种均无推送", stale));
+        }
+
+        if silent > self.cfg.silence_pause {
+            return Health::Degraded(format!("已静默 {:.1}s", silent.as_secs_f64()));
+        }
+        Health::Healthy
+    }
+}
@@ -1,18 +1,27 @@
-use futures_util::{SinkExt, StreamExt};
-use log::{info, error, warn};
-use tokio_tungstenite::tungstenite::Message;
-use futures_util::stream::{SplitStream, SplitSink};
-use tokio::net::TcpStream;
-use tokio_tungstenite::WebSocketStream;
-use tokio_native_tls::TlsStream;
+use futures_util::future::{BoxFuture, FutureExt};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use log::{debug, info, error, warn};
 use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, RwLock};
-use crate::okx::protocol::{self, WsRouter, AccountData};
-use crate::okx::market_data::Ticker;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use crate::okx::order_manager::{Fill, OrderManager, OrderState, OrderUpdate};
+use crate::okx::protocol::{Endpoint, self, CancelRequest, ChannelType, OpPacket, OrderRequest, OrderSize, Side, WsRouter, AccountData};
+use crate::okx::correlation::OpResult;
+use crate::okx::error::OkxError;
+use crate::okx::supervisor::{Disconnect, DisconnectReason, Inbound, Link, LinkEvent};
+use crate::okx::instruments::InstrumentRegistry;
+use crate::okx::rest::RestClient;
+use crate::okx::backfill::{self, BackfillConfig, WarmUpData};
+use crate::okx::bar_builder::{self, BarBuilder, BarSource, ClosedBar};
+use crate::okx::market_data::{BookData, Candle, Instrument, Ticker, Trade};
+use crate::okx::order_book::OrderBooks;
+use crate::okx::order_id;
+use crate::okx::trade_tape::TradeTape;
+use crate::okx::trade_data::Order;
+use crate::okx::watchdog::{ConnectionWatchdog, Health, WatchdogConfig};
 use crate::utils::logger::LogFormatter;
-
-type WsWriteStream = SplitSink<WebSocketStream<TlsStream<TcpStream>>, Message>;
-type WsReadStream = SplitStream<WebSocketStream<TlsStream<TcpStream>>>;
+use crate::utils::time;
 
 // ⚙️ 策略核心参数 (Strategy Config)
 const ROUND_TRIP_COST: f64 = 0.004; // 0.4% 硬成本 (含滑点)
@@ -20,109 +29,583 @@ const BUY_CRASH_THRESHOLD: f64 = -0.025; // 5s跌幅 > 2.5% 才买
 const TAKE_PROFIT_NET: f64 = 0.01; // 净赚 > 1.0% 才卖
 const STOP_LOSS_NET: f64 = -0.03; // 净亏 > 3.0% 止损
 const BET_SIZE_USDT: f64 = 25.0; // 单笔 25 U
+const MAX_ENTRY_SLIPPAGE: f64 = 0.005; // 按深度预估的吃单滑点预算 0.5%，超出则缩单
+const MIN_BET_SIZE_USDT: f64 = 10.0; // 缩单后低于 10 U 直接放弃
+const CAPITULATION_WINDOW_MS: i64 = 5_000; // 抛售放量的统计窗口 (与跌幅窗口一致)
+const CAPITULATION_SELL_RATIO: f64 = 0.7; // 主动卖出金额占比 > 70%
+const CAPITULATION_MIN_SELL_USDT: f64 = 5_000.0; // 且主动卖出金额 > 5000 U
 const MAX_POSITIONS: usize = 3; // 最大持仓数
+const STRATEGY_CODE: &str = "fc"; // clOrdId 中的策略代码 (Flash Crash)
+const EXIT_RETRY_DELAY_MS: i64 = 2_000; // 平仓单失败后的重试间隔
+const EXIT_ESCALATE_AFTER: u32 = 3; // 连续失败次数超过后告警并放慢重试
+const EXIT_ESCALATED_DELAY_MS: i64 = 30_000;
+const BAR_GRACE_MS: i64 = 1_500; // K 线收盘后继续接受迟到成交的宽限期
+const BAR_HISTORY_LEN: usize = 500; // 每个品种 / 周期保留的已收盘 K 线根数
+// 行情重连后的回补期间暂停读取行情 (推送堆积在 socket 中)，超出预算就停止回补
+const WARM_UP_BUDGET: std::time::Duration = std::time::Duration::from_secs(15);
 
+/// 📦 持仓只由成交回报建立 / 累加，绝不在下单时乐观写入
 #[derive(Debug, Clone)]
 struct Position {
     inst_id: String,
-    entry_price: f64, // 必须是 Ask1 (实际买入成本)
-    entry_ts: i64,
+    entry_price: f64, // 成交均价 (真实买入成本)
+    size: f64,        // 累计成交数量 (基础币)
+    fees: HashMap<String, f64>, // 按币种累计的手续费 (负数为扣费)
+    entry_ts: i64,    // 首笔成交时间
+    sold: f64,        // 平仓单已成交数量
+    exit: Option<ExitOrder>,
+    slippage: Option<EntrySlippage>, // 开仓时的滑点预估
+}
+
+/// 🚪 平仓进度：持仓只在卖单成交后移除
+#[derive(Debug, Clone)]
+struct ExitOrder {
+    reason: &'static str,
+    signal: &'static str, // clOrdId 中的信号代码
+    cl_ord_id: Option<String>, // 在途的卖单
+    attempts: u32,
+    retry_at: i64,
+}
+
+impl Position {
+    fn add_fill(&mut self, fill: &Fill) {
+        let total = self.size + fill.sz;
+        if total > 0.0 {
+            self.entry_price = (self.entry_price * self.size + fill.px * fill.sz) / total;
+        }
+        self.size = total;
+        *self.fees.entry(fill.fee_ccy.clone()).or_insert(0.0) += fill.fee;
+    }
+
+    /// 实际可卖数量 = 买入成交量 + 基础币手续费 (扣费为负) - 已卖出
+    fn sellable_qty(&self) -> f64 {
+        let base_ccy = self.inst_id.split('-').next().unwrap_or_default();
+        let base_fee = self.fees.get(base_ccy).copied().unwrap_or(0.0);
+        (self.size + base_fee - self.sold).max(0.0)
+    }
+}
+
+/// ⏳ 已发出、尚未终结的开仓意图 (防止订单在途时重复开仓)
+#[derive(Debug, Clone)]
+struct PendingEntry {
+    cl_ord_id: String,
+    since_ts: i64,
+    slippage: EntrySlippage,
+}
+
+/// 📐 开仓前按本地深度估算的吃单结果，成交后与实际均价对比
+#[derive(Debug, Clone, Copy)]
+struct EntrySlippage {
+    ref_px: f64,   // 下单时的卖一价
+    est_px: f64,   // 预估成交均价
+    notional: f64, // 实际下单金额 (可能被缩单)
+}
+
+impl EntrySlippage {
+    fn estimated(&self) -> f64 {
+        (self.est_px - self.ref_px) / self.ref_px
+    }
+
+    fn actual(&self, fill_px: f64) -> f64 {
+        (fill_px - self.ref_px) / self.ref_px
+    }
 }
 
 pub struct StrategyState {
     pub usdt_balance: RwLock<f64>,
     positions: RwLock<HashMap<String, Position>>,
+    pending_entries: RwLock<HashMap<String, PendingEntry>>,
 }
 
 pub struct MarketStrategy {
     price_history: RwLock<HashMap<String, VecDeque<(i64, f64)>>>,
     state: Arc<StrategyState>,
+    orders: Mutex<OrderManager>,
+    instruments: Arc<InstrumentRegistry>,
+    books: OrderBooks,
+    tape: TradeTape,
+    bars: Mutex<Vec<BarBuilder>>,
+    // (品种, 周期, 来源) -> 已收盘 K 线 (旧 -> 新)
+    bar_history: RwLock<HashMap<(String, i64, BarSource), VecDeque<Candle>>>,
+    watchdog_cfg: WatchdogConfig,
+    // 连接不健康时暂停开新仓 (平仓逻辑不受影响)
+    entries_paused: AtomicBool,
+    // 私有连接在线 (重连期间不下单 / 不平仓)
+    private_online: AtomicBool,
+    // 私有连接断开时未能发出的撤单
+    deferred_cancels: Mutex<Vec<OpPacket>>,
 }
 
+/// 在途交易请求的回执
+type Responses = FuturesUnordered<BoxFuture<'static, OpResult>>;
+
 impl MarketStrategy {
-    pub fn new() -> Self {
+    pub fn new(watchdog_cfg: WatchdogConfig, instruments: Arc<InstrumentRegistry>, bar_intervals: &[i64]) -> Self {
         MarketStrategy {
+            watchdog_cfg,
+            instruments,
+            books: OrderBooks::new(),
+            tape: TradeTape::new(),
+            bars: Mutex::new(bar_intervals.iter().map(|ms| BarBuilder::new(*ms, BAR_GRACE_MS)).collect()),
+            bar_history: RwLock::new(HashMap::new()),
+            entries_paused: AtomicBool::new(false),
+            private_online: AtomicBool::new(true),
+            deferred_cancels: Mutex::new(Vec::new()),
             price_history: RwLock::new(HashMap::new()),
+            orders: Mutex::new(OrderManager::new()),
             state: Arc::new(StrategyState {
                 usdt_balance: RwLock::new(0.0),
                 positions: RwLock::new(HashMap::new()),
+                pending_entries: RwLock::new(HashMap::new()),
             }),
         }
     }
 
+    /// 🔄 事件循环：三条连接互不阻塞
+    /// 任意一条断开 (Close 帧 / 流结束 / 读写出错 / 看门狗判定失活) 时通知策略并在后台重连，
+    /// 其余连接照常收发与心跳；只有重连遇到致命错误才返回
     pub async fn run(
-        &self,
-        mut read_pub: WsReadStream,
-        mut write_pub: WsWriteStream,
-        mut read_priv: WsReadStream,
-        mut write_priv: WsWriteStream
-    ) {
+        &self, public: &mut Link, private: &mut Link, business: &mut Link,
+        rest: &RestClient, watchlist: &[&str], backfill_cfg: &BackfillConfig,
+    ) -> OkxError {
         info!("🧠 [狙击引擎] Flash Crash Sniper 启动 | 费率风控: 开 | 精度: Ask/Bid");
 
-        let mut heartbeat_interval = tokio::time::interval(tokio::time::Duration::from_secs(15));
+        let mut heartbeat_interval = tokio::time::interval(self.watchdog_cfg.ping_interval);
+        let mut health_interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+        let mut wd_pub = ConnectionWatchdog::new(self.watchdog_cfg.clone(), true);
+        let mut wd_priv = ConnectionWatchdog::new(self.watchdog_cfg.clone(), false);
+        // K 线推送频率不固定，业务连接只做心跳存活检测
+        let mut wd_biz = ConnectionWatchdog::new(self.watchdog_cfg.clone(), false);
+        // 在途交易请求的回执 (超时 / 断线也会返回，见 ResponseHandle::wait)
+        let mut responses: Responses = FuturesUnordered::new();
+        // 行情重连后的历史回补 (与其他连接并发进行；期间暂停开仓，行情推送先缓存，回补写入后再按序处理)
+        let mut warming: Option<(BoxFuture<'_, WarmUpData>, tokio::time::Instant)> = None;
+        let mut backlog: Vec<WsRouter> = Vec::new();
+        self.private_online.store(private.is_up(), Ordering::Relaxed);
 
         loop {
             tokio::select! {
-                // 心跳
+                // 心跳 (重连中的连接跳过)
                 _ = heartbeat_interval.tick() => {
-                    let _ = write_pub.send(Message::Text("ping".to_string())).await;
-                    let _ = write_priv.send(Message::Text("ping".to_string())).await;
+                    self.ping(public, &mut wd_pub).await;
+                    self.ping(private, &mut wd_priv).await;
+                    self.ping(business, &mut wd_biz).await;
+                }
+                // 🐕 存活检测：静默先暂停开仓，再升级为强制重连；顺带重发失败的订阅
+                _ = health_interval.tick() => {
+                    let pub_health = match self.check_health(public, &mut wd_pub) {
+                        Health::Healthy if warming.is_some() => Health::Degraded("历史回补中".to_string()),
+                        health => health,
+                    };
+                    let priv_health = self.check_health(private, &mut wd_priv);
+                    self.check_health(business, &mut wd_biz);
+                    self.set_entries_paused(pub_health != Health::Healthy || priv_health != Health::Healthy, &pub_health);
+
+                    self.orders.lock().unwrap().prune();
+
+                    for link in [&mut *public, &mut *private, &mut *business] {
+                        let Some(conn) = link.conn() else { continue };
+                        if let Err(e) = conn.maintain().await {
+                            self.lose(link, DisconnectReason::Error(format!("连接维护失败: {}", e)));
+                        }
+                    }
+
+                    // 长时间没有成交的品种按时间收盘
+                    let closed: Vec<ClosedBar> = {
+                        let now = time::get_timestamp_ms();
+                        self.bars.lock().unwrap().iter_mut().flat_map(|b| b.flush(now)).collect()
+                    };
+                    self.on_bars_closed(closed);
+                }
+                // 交易请求回执：下单被拒 / 超时 / 撤单查无此单；超时未确认的订单补发撤单，防止它之后在交易所意外成交
+                Some(result) = responses.next(), if !responses.is_empty() => {
+                    let updates = self.orders.lock().unwrap().on_result(&result);
+                    let cancels = self.cancel_packets(&updates);
+                    self.on_order_updates(updates);
+                    for packet in cancels {
+                        self.send_request(private, packet, &mut responses).await;
+                    }
+                }
+                // 回补完成：先写入历史数据，再恢复读取行情 (重叠的成交按 tradeId 去重)
+                data = async { warming.as_mut().unwrap().0.as_mut().await }, if warming.is_some() => {
+                    let started = warming.take().map(|(_, started)| started).unwrap_or_else(tokio::time::Instant::now);
+                    self.apply_warm_up(data, started);
+                    wd_pub = ConnectionWatchdog::new(self.watchdog_cfg.clone(), true);
+                    for router in std::mem::take(&mut backlog) {
+                        if let Some(packet) = self.process_public_message(router, &mut wd_pub) {
+                            self.send_request(private, packet, &mut responses).await;
+                        }
+                    }
+                    self.resync_books(public).await;
                 }
                 // 行情消息
-                msg_res = read_pub.next() => {
-                    if let Some(Ok(Message::Text(text))) = msg_res {
-                        if text == "pong" { continue; }
-                        if let Some(order_json) = self.process_public_message(&text) {
-                            if let Err(e) = write_priv.send(Message::Text(order_json)).await {
-                                error!("❌ 下单失败: {}", e);
+                event = public.recv() => match event {
+                    LinkEvent::Frame(frame) => match Inbound::from_frame(Endpoint::Public, frame) {
+                        Inbound::Text(text) => {
+                            if text == "pong" { wd_pub.on_pong(); continue; }
+                            wd_pub.on_message();
+                            let Ok(router) = WsRouter::parse(&text) else { continue };
+                            if public.conn().is_some_and(|c| c.on_event(&router)) { continue; }
+                            if warming.is_some() {
+                                // 回补期间持仓照常检查平仓，止损不等回补
+                                if let Some(packet) = self.check_exits(&router) {
+                                    self.send_request(private, packet, &mut responses).await;
+                                }
+                                backlog.push(router);
+                                continue;
                             }
+                            if let Some(packet) = self.process_public_message(router, &mut wd_pub) {
+                                self.send_request(private, packet, &mut responses).await;
+                            }
+                            self.resync_books(public).await;
                         }
+                        Inbound::Control => {}
+                        Inbound::Lost(reason) => self.lose(public, reason),
+                    },
+                    LinkEvent::Reconnected => {
+                        wd_pub = ConnectionWatchdog::new(self.watchdog_cfg.clone(), true);
+                        // 断线期间的缺口用历史数据补上，避免重连后一段时间内看不见行情
+                        self.set_entries_paused(true, &Health::Degraded("历史回补中".to_string()));
+                        backlog.clear();
+                        warming = Some((backfill::fetch(rest, watchlist, backfill_cfg, WARM_UP_BUDGET).boxed(), tokio::time::Instant::now()));
                     }
-                }
+                    LinkEvent::Fatal(e) => return e,
+                },
+                // 业务消息 (K 线)
+                event = business.recv() => match event {
+                    LinkEvent::Frame(frame) => match Inbound::from_frame(Endpoint::Business, frame) {
+                        Inbound::Text(text) => {
+                            if text == "pong" { wd_biz.on_pong(); continue; }
+                            wd_biz.on_message();
+                            let Ok(router) = WsRouter::parse(&text) else { continue };
+                            if business.conn().is_some_and(|c| c.on_event(&router)) { continue; }
+                            self.process_business_message(router);
+                        }
+                        Inbound::Control => {}
+                        Inbound::Lost(reason) => self.lose(business, reason),
+                    },
+                    LinkEvent::Reconnected => wd_biz = ConnectionWatchdog::new(self.watchdog_cfg.clone(), false),
+                    LinkEvent::Fatal(e) => return e,
+                },
                 // 账户消息
-                msg_res = read_priv.next() => {
-                    if let Some(Ok(Message::Text(text))) = msg_res {
-                        if text == "pong" { continue; }
-                        self.process_private_message(&text);
+                event = private.recv() => match event {
+                    LinkEvent::Frame(frame) => match Inbound::from_frame(Endpoint::Private, frame) {
+                        Inbound::Text(text) => {
+                            if text == "pong" { wd_priv.on_pong(); continue; }
+                            wd_priv.on_message();
+                            let Ok(router) = WsRouter::parse(&text) else { continue };
+                            if private.conn().is_some_and(|c| c.on_event(&router)) { continue; }
+                            self.process_private_message(router);
+                        }
+                        Inbound::Control => {}
+                        Inbound::Lost(reason) => self.lose(private, reason),
+                    },
+                    LinkEvent::Reconnected => {
+                        wd_priv = ConnectionWatchdog::new(self.watchdog_cfg.clone(), false);
+                        self.private_online.store(true, Ordering::Relaxed);
+                        info!("🔗 [交易] 已重连，恢复交易");
+                        // 断线时未确认订单的撤单，在新连接上补发
+                        let deferred: Vec<OpPacket> = std::mem::take(&mut *self.deferred_cancels.lock().unwrap());
+                        for packet in deferred {
+                            self.send_request(private, packet, &mut responses).await;
+                        }
                     }
-                }
+                    LinkEvent::Fatal(e) => return e,
+                },
             }
         }
     }
 
-    fn process_public_message(&self, text: &str) -> Option<String> {
-        let router: WsRouter = match serde_json::from_str(text) {
-            Ok(r) => r,
-            Err(_) => return None,
+    /// 💓 发送心跳 (重连中的连接跳过)
+    async fn ping(&self, link: &mut Link, watchdog: &mut ConnectionWatchdog) {
+        let Some(conn) = link.conn() else { return };
+        match conn.send("ping".to_string()).await {
+            Ok(()) => watchdog.on_ping(),
+            Err(e) => self.lose(link, DisconnectReason::Error(format!("心跳发送失败: {}", e))),
+        }
+    }
+
+    /// 🐕 看门狗检查，判定失活则断开重连；重连中的连接视为不健康
+    fn check_health(&self, link: &mut Link, watchdog: &mut ConnectionWatchdog) -> Health {
+        if !link.is_up() {
+            return Health::Degraded("重连中".to_string());
+        }
+        let health = watchdog.check();
+        if let Health::Dead(reason) = &health {
+            self.lose(link, DisconnectReason::Watchdog(reason.clone()));
+        }
+        health
+    }
+
+    /// 📤 通过私有连接发出交易请求，回执句柄交给事件循环等待
+    /// 私有连接重连中 / 发送失败时，撤单留到重连后补发 (下单在断线期间不会产生)
+    async fn send_request(&self, private: &mut Link, packet: OpPacket, responses: &mut Responses) {
+        let Some(conn) = private.conn() else {
+            self.defer(packet);
+            return;
         };
+        match conn.request(&packet).await {
+            Ok(handle) => responses.push(handle.wait().boxed()),
+            Err(e) => {
+                let reason = DisconnectReason::Error(format!("{} 发送失败: {}", packet.op, e));
+                self.defer(packet);
+                self.lose(private, reason);
+            }
+        }
+    }
+
+    fn defer(&self, packet: OpPacket) {
+        if packet.op == "cancel-order" {
+            self.defer_cancel(packet);
+        } else {
+            // 下单未发出: 断线处理会把未确认的订单标记超时并补发撤单
+            error!("❌ [交易] 私有连接不可用，{} {} 未发出", packet.op, packet.cl_ord_id());
+        }
+    }
+
+    /// 撤单排队等重连后补发 (同一订单只保留一份)
+    fn defer_cancel(&self, packet: OpPacket) {
+        let mut deferred = self.deferred_cancels.lock().unwrap();
+        if !deferred.iter().any(|p| p.cl_ord_id() == packet.cl_ord_id()) {
+            deferred.push(packet);
+        }
+    }
+
+    /// 🔌 连接丢失：通知策略，守护者转入后台重连
+    fn lose(&self, link: &mut Link, reason: DisconnectReason) {
+        let lost = Disconnect { endpoint: link.endpoint(), reason };
+        warn!("🔌 [{:?}] 连接中断，后台重连中...", lost.endpoint);
+        self.on_disconnect(&lost);
+        link.reconnect();
+    }
+
+    /// ⏸️ 开仓开关 (只在状态变化时打日志)
+    fn set_entries_paused(&self, paused: bool, pub_health: &Health) {
+        let was_paused = self.entries_paused.swap(paused, Ordering::Relaxed);
+        if paused && !was_paused {
+            warn!("⏸️ [风控] 连接不健康 ({:?})，暂停开新仓", pub_health);
+        } else if !paused && was_paused {
+            info!("▶️ [风控] 连接恢复，允许开新仓");
+        }
+    }
+
+    /// 🕳️ [断线通知]
+    /// 行情断线：重连期间存在缺口，丢弃旧的价格窗口，避免用断线前的价格判断暴跌
+    /// 交易断线：订单回报与余额都不可信，清零余额，等重连后账户频道推送新快照前不再开仓
+    fn on_disconnect(&self, lost: &Disconnect) {
+        match lost.endpoint {
+            Endpoint::Public => {
+                warn!("📉 [行情] 断开: {}", lost.reason);
+                self.price_history.write().unwrap().clear();
+                self.books.clear();
+                self.tape.clear();
+                for builder in self.bars.lock().unwrap().iter_mut() {
+                    if builder.late_count() > 0 {
+                        info!("🐢 [K线] {} 合成累计丢弃迟到数据 {} 条", bar_builder::format_interval(builder.interval_ms()), builder.late_count());
+                    }
+                    builder.clear();
+                }
+                warn!("🕳️ [行情] 数据存在缺口，已清空价格窗口、本地深度、成交流与未收盘 K 线");
+            }
+            Endpoint::Private => {
+                error!("⛔ [交易] 断开: {} —— 停止交易，等待重连", lost.reason);
+                self.private_online.store(false, Ordering::Relaxed);
+                *self.state.usdt_balance.write().unwrap() = 0.0;
+                // 未确认的订单可能已到达交易所，重连后补发撤单 (撤单结果未知的超时订单一并重发)
+                let timed_out = self.orders.lock().unwrap().on_connection_lost();
+                let cancels = self.cancel_packets(&timed_out);
+                self.on_order_updates(timed_out);
+                for packet in cancels {
+                    self.defer_cancel(packet);
+                }
+            }
+            Endpoint::Business => warn!("📊 [业务] 断开: {}", lost.reason),
+        }
+    }
+
+    fn process_public_message(&self, router: WsRouter, watchdog: &mut ConnectionWatchdog) -> Option<OpPacket> {
+        if let Some(e) = router.error() {
+            error!("❌ [行情] {}", e);
+            return None;
+        }
         if let Some(arg) = router.arg {
             if arg.channel == "tickers" {
                 if let Some(raw_data) = router.data {
                     if let Ok(tickers) = serde_json::from_str::<Vec<Ticker>>(raw_data.get()) {
                         for t in tickers {
+                            watchdog.on_instrument_update(&t.inst_id);
                             if let Some(order) = self.analyze_ticker(t) { return Some(order); }
                         }
                     }
                 }
+            } else if ChannelType::book_channel(&arg.channel).is_some() {
+                if let Some(raw_data) = router.data {
+                    match serde_json::from_str::<Vec<BookData>>(raw_data.get()) {
+                        Ok(data) => self.books.on_push(&arg, router.action.as_deref(), data),
+                        Err(e) => error!("❌ [深度] 推送解析失败: {}", e),
+                    }
+                }
+            } else if arg.channel == "trades" {
+                if let Some(raw_data) = router.data {
+                    match serde_json::from_str::<Vec<Trade>>(raw_data.get()) {
+                        Ok(trades) => self.ingest_trades(trades),
+                        Err(e) => error!("❌ [成交流] 推送解析失败: {}", e),
+                    }
+                }
+            } else if arg.channel == "instruments" {
+                if let Some(raw_data) = router.data {
+                    match serde_json::from_str::<Vec<Instrument>>(raw_data.get()) {
+                        Ok(list) => self.instruments.apply(list),
+                        Err(e) => error!("❌ [产品] 推送解析失败: {}", e),
+                    }
+                }
             }
         }
         None
     }
 
-    fn process_private_message(&self, text: &str) {
-        let router: WsRouter = match serde_json::from_str(text) {
-            Ok(r) => r,
-            Err(_) => return,
+    /// 🚑 回补期间的 ticker 只检查持仓品种的平仓 (开仓已暂停，价格窗口等回补写入后随缓存的推送一起更新)
+    fn check_exits(&self, router: &WsRouter) -> Option<OpPacket> {
+        if router.arg.as_ref()?.channel != "tickers" {
+            return None;
+        }
+        let tickers: Vec<Ticker> = serde_json::from_str(router.data.as_ref()?.get()).ok()?;
+        let held: Vec<Ticker> = {
+            let pos_map = self.state.positions.read().unwrap();
+            tickers.into_iter().filter(|t| pos_map.contains_key(&t.inst_id)).collect()
+        };
+        held.into_iter().find_map(|t| self.analyze_ticker(t))
+    }
+
+    /// 深度校验失败：退订再订阅，拿一份新快照
+    async fn resync_books(&self, public: &mut Link) {
+        for arg in self.books.take_resync() {
+            let Some(conn) = public.conn() else { break };
+            let result = match conn.unsubscribe(vec![arg.clone()]).await {
+                Ok(()) => conn.subscribe(vec![arg]).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.lose(public, DisconnectReason::Error(format!("深度重订阅失败: {}", e)));
+            }
+        }
+    }
+
+    /// 成交 -> 成交流 (去重) -> K 线合成
+    fn ingest_trades(&self, trades: Vec<Trade>) {
+        let fresh = self.tape.on_trades(trades);
+        let closed: Vec<ClosedBar> = {
+            let mut bars = self.bars.lock().unwrap();
+            fresh.iter().flat_map(|t| bars.iter_mut().flat_map(|b| b.on_trade(t)).collect::<Vec<_>>()).collect()
         };
+        self.on_bars_closed(closed);
+    }
+
+    /// 🔥 [预热] 启动时、建立连接之前，用 REST 历史数据填充策略状态
+    pub async fn warm_up(&self, rest: &RestClient, watchlist: &[&str], cfg: &BackfillConfig) {
+        let started = tokio::time::Instant::now();
+        let data = backfill::fetch(rest, watchlist, cfg, WARM_UP_BUDGET).await;
+        self.apply_warm_up(data, started);
+    }
+
+    /// 回补数据写入策略状态: 成交 -> 成交流 / 本地 K 线 / 价格窗口；K 线 -> 交易所 K 线历史
+    /// 缺数据的品种只是暂时"看不见"，不阻止交易
+    fn apply_warm_up(&self, data: WarmUpData, started: tokio::time::Instant) {
+        let (instruments, mut trade_count, mut candle_count) = (data.trades.len(), 0, 0);
+        for (inst_id, trades) in data.trades {
+            trade_count += trades.len();
+            self.seed_price_history(&inst_id, &trades);
+            self.ingest_trades(trades);
+        }
+        for (inst_id, bar, candles) in data.candles {
+            let Some(interval_ms) = bar_builder::parse_interval(&bar) else { continue };
+            candle_count += candles.len();
+            self.on_bars_closed(candles.into_iter()
+                .map(|candle| ClosedBar { inst_id: inst_id.clone(), interval_ms, source: BarSource::Exchange, candle, filled: false })
+                .collect());
+        }
+        info!(
+            "🔥 [预热] 完成: {} 个品种 | 成交 {} 笔 | K 线 {} 根 | 耗时 {}ms",
+            instruments, trade_count, candle_count, started.elapsed().as_millis()
+        );
+    }
+
+    /// 用回补成交填充暴跌侦测的 5s 价格窗口 (只取窗口内的成交)
+    fn seed_price_history(&self, inst_id: &str, trades: &[Trade]) {
+        let now = time::get_timestamp_ms();
+        let mut history_map = self.price_history.write().unwrap();
+        let queue = history_map.entry(inst_id.to_string()).or_insert(VecDeque::with_capacity(20));
+        for t in trades {
+            let Ok(ts) = t.ts.parse::<i64>() else { continue };
+            if now - ts <= 5000 && queue.back().is_none_or(|(last, _)| ts >= *last) {
+                queue.push_back((ts, t.px));
+            }
+        }
+    }
+
+    /// 🕯️ business 端点: OKX candle 频道，只取已收盘 (confirm=1) 的 K 线
+    fn process_business_message(&self, router: WsRouter) {
+        if let Some(e) = router.error() {
+            error!("❌ [业务] {}", e);
+            return;
+        }
+        let Some(arg) = router.arg else { return };
+        let (Some(bar), Some(inst_id)) = (arg.candle_bar(), arg.inst_id.as_deref()) else { return };
+        let Some(interval_ms) = bar_builder::parse_interval(bar) else { return }; // 月线长度不固定，暂不处理
+        let Some(raw_data) = router.data else { return };
+        let rows: Vec<Vec<String>> = match serde_json::from_str(raw_data.get()) {
+            Ok(rows) => rows,
+            Err(e) => { error!("❌ [K线] 推送解析失败: {}", e); return; }
+        };
+        let closed = rows.iter()
+            .filter_map(|row| Candle::from_row(row))
+            .filter(|c| c.confirmed)
+            .map(|candle| ClosedBar { inst_id: inst_id.to_string(), interval_ms, source: BarSource::Exchange, candle, filled: false })
+            .collect();
+        self.on_bars_closed(closed);
+    }
+
+    /// 📣 [收盘事件] 本地合成 / 交易所推送的已收盘 K 线统一从这里进入策略
+    fn on_bars_closed(&self, bars: Vec<ClosedBar>) {
+        if bars.is_empty() { return; }
+        let mut history = self.bar_history.write().unwrap();
+        for bar in bars {
+            let c = &bar.candle;
+            debug!(
+                "🕯️ [K线] {} {} {:?}{} | O {} H {} L {} C {} | V {} ({:.0} U)",
+                bar.inst_id, bar_builder::format_interval(bar.interval_ms), bar.source,
+                if bar.filled { " (补齐)" } else { "" }, c.open, c.high, c.low, c.close, c.vol, c.vol_quote
+            );
+            let queue = history.entry((bar.inst_id, bar.interval_ms, bar.source)).or_default();
+            // 交易所重复推送同一根 K 线时以最新一次为准，更早的直接忽略
+            match queue.back() {
+                Some(last) if last.ts > c.ts => continue,
+                Some(last) if last.ts == c.ts => { queue.pop_back(); }
+                _ => {}
+            }
+            queue.push_back(bar.candle);
+            while queue.len() > BAR_HISTORY_LEN {
+                queue.pop_front();
+            }
+        }
+    }
+
+    fn process_private_message(&self, router: WsRouter) {
+        if let Some(e) = router.error() {
+            error!("❌ [交易] {}", e);
+            return;
+        }
         if let Some(arg) = router.arg {
-            if arg.channel == "account" {
-                self.update_balance(router.data.as_deref());
+            match arg.channel.as_str() {
+                "account" => self.update_balance(router.data.as_deref()),
+                "orders" => self.update_orders(router.data.as_deref()),
+                _ => {}
             }
         }
     }
 
     // 🕵️ [核心逻辑]
-    fn analyze_ticker(&self, ticker: Ticker) -> Option<String> {
+    fn analyze_ticker(&self, ticker: Ticker) -> Option<OpPacket> {
         let inst_id = ticker.inst_id.clone();
 
         // 🎯 [精确价格]
@@ -132,46 +615,78 @@ impl MarketStrategy {
         let buy_cost_price = ticker.ask_px;
         let sell_revenue_price = ticker.bid_px;
 
-        let now = chrono::Utc::now().timestamp_millis();
+        // 交易所时间戳只用于修正本机时钟下界，延迟/新鲜度一律用校准后的时间
+        let remote_ts = ticker.ts.parse::<i64>().unwrap_or(0);
+        time::observe_exchange_ts(remote_ts);
+        let now = time::get_timestamp_ms();
 
         let log_msg = LogFormatter::format_ticker(&ticker);
         info!("{}", log_msg);
 
         // 延迟风控
-        let remote_ts = ticker.ts.parse::<i64>().unwrap_or(0);
         if now - remote_ts > 2000 { return None; }
 
         // 1. 卖出逻辑 (如果有持仓)
         {
             let mut pos_map = self.state.positions.write().unwrap();
 
-            if let Some(pos) = pos_map.get(&inst_id) {
-                // 计算利润: (当前卖一价 - 当初买一价) / 当初买一价
-                let gross_profit = (sell_revenue_price - pos.entry_price) / pos.entry_price;
-                let net_profit = gross_profit - ROUND_TRIP_COST;
+            if let Some(pos) = pos_map.get_mut(&inst_id) {
+                if pos.exit.is_none() {
+                    // 计算利润: (当前卖一价 - 成交均价) / 成交均价
+                    let gross_profit = (sell_revenue_price - pos.entry_price) / pos.entry_price;
+                    let net_profit = gross_profit - ROUND_TRIP_COST;
 
-                // 止盈
-                if net_profit > TAKE_PROFIT_NET {
-                    warn!("💎 [止盈] {} 净赚 {:.2}% | 卖价: {}", inst_id, net_profit*100.0, sell_revenue_price);
-                    pos_map.remove(&inst_id);
-                    return Some(protocol::create_order_packet(&inst_id, "sell", "0", None));
+                    let (reason, signal) = if net_profit > TAKE_PROFIT_NET {
+                        // 止盈
+                        warn!("💎 [止盈] {} 净赚 {:.2}% | 卖价: {}", inst_id, net_profit*100.0, sell_revenue_price);
+                        ("止盈", "tp")
+                    } else if net_profit < STOP_LOSS_NET {
+                        // 止损
+                        error!("🩸 [止损] {} 净亏 {:.2}% | 卖价: {}", inst_id, net_profit*100.0, sell_revenue_price);
+                        ("止损", "sl")
+                    } else if now - pos.entry_ts > 600_000 {
+                        // 超时 (10分钟)
+                        warn!("⏰ [超时] {} 平仓", pos.inst_id);
+                        ("超时", "to")
+                    } else {
+                        return None;
+                    };
+                    // 一旦决定平仓就坚持到底，重试时不再重新判断盈亏
+                    pos.exit = Some(ExitOrder { reason, signal, cl_ord_id: None, attempts: 0, retry_at: 0 });
                 }
-                // 止损
-                if net_profit < STOP_LOSS_NET {
-                    error!("🩸 [止损] {} 净亏 {:.2}% | 卖价: {}", inst_id, net_profit*100.0, sell_revenue_price);
-                    pos_map.remove(&inst_id);
-                    return Some(protocol::create_order_packet(&inst_id, "sell", "0", None));
-                }
-                // 超时 (10分钟)
-                if now - pos.entry_ts > 600_000 {
-                    warn!("⏰ [超时] {} 平仓", inst_id);
-                    pos_map.remove(&inst_id);
-                    return Some(protocol::create_order_packet(&inst_id, "sell", "0", None));
+
+                // 平仓单在途 / 等待重试 / 私有连接重连中
+                if pos.exit.as_ref().is_some_and(|e| e.cl_ord_id.is_some() || now < e.retry_at) {
+                    return None;
                 }
-                return None;
+                if !self.private_online.load(Ordering::Relaxed) { return None; }
+                let Some(inst) = self.instruments.get(&inst_id) else {
+                    error!("❌ [平仓] {} 缺少产品信息，无法计算卖出数量", inst_id);
+                    return None;
+                };
+                // 停牌等状态下等待恢复 (状态变化由注册表提示)
+                if !inst.is_tradable() { return None; }
+                let qty = match inst.check_size(pos.sellable_qty()) {
+                    Ok(qty) => qty,
+                    Err(e) => {
+                        warn!("🧹 [平仓] {}，按残渣处理并移除持仓", e);
+                        pos_map.remove(&inst_id);
+                        return None;
+                    }
+                };
+                let signal = pos.exit.as_ref()?.signal;
+                let packet = self.order_packet(&inst, Side::Sell, OrderSize::Base(qty), signal)?;
+                let exit = pos.exit.as_mut()?;
+                exit.cl_ord_id = Some(packet.cl_ord_id().to_string());
+                exit.attempts += 1;
+                info!("📤 [平仓] {} ({}) 卖出 {} | 第 {} 次", inst_id, exit.reason, inst.format_size(qty), exit.attempts);
+                return Some(packet);
             }
-            if pos_map.len() >= MAX_POSITIONS { return None; }
+            let pending = self.state.pending_entries.read().unwrap();
+            if pending.contains_key(&inst_id) { return None; }
+            if pos_map.len() + pending.len() >= MAX_POSITIONS { return None; }
         }
+        if self.entries_paused.load(Ordering::Relaxed) || !self.private_online.load(Ordering::Relaxed) { return None; }
 
         // 2. 买入逻辑 (如果没持仓)
         let mut history_map = self.price_history.write().unwrap();
@@ -190,31 +705,256 @@ impl MarketStrategy {
 
             if change_pct < BUY_CRASH_THRESHOLD {
                 info!("📉 [暴跌侦测] {} 5s跌幅 {:.2}%", inst_id, change_pct * 100.0);
+                if let Some(Some(depth)) = self.books.with_book(&inst_id, |b| b.depth_within(0.01)) {
+                    info!("📚 [深度] {} ±1% 买盘 {:.1} U | 卖盘 {:.1} U", inst_id, depth.bid_notional, depth.ask_notional);
+                }
+                if !self.is_capitulation(&inst_id, now) { return None; }
 
                 let balance = *self.state.usdt_balance.read().unwrap();
 
                 if balance >= BET_SIZE_USDT {
-                    {
-                        let mut pos_map = self.state.positions.write().unwrap();
-                        // ✅ [修正] 记录持仓成本时，必须记录 buy_cost_price (Ask1)
-                        // 这样后续计算盈亏才是真实的
-                        pos_map.insert(inst_id.clone(), Position {
-                            inst_id: inst_id.clone(),
-                            entry_price: buy_cost_price,
-                            entry_ts: now,
-                        });
-                    }
-                    warn!("🚀 [狙击] 锁定 Ask1: {} | Last: {}", buy_cost_price, last_price);
+                    warn!("🚀 [狙击] 参考 Ask1: {} | Last: {}", buy_cost_price, last_price);
 
-                    return Some(protocol::create_order_packet(
-                        &inst_id, "buy", &BET_SIZE_USDT.to_string(), None
-                    ));
+                    // 持仓等成交回报再建立，这里只登记开仓意图
+                    let Some(inst) = self.instruments.get(&inst_id) else {
+                        warn!("⚠️ [狙击] {} 缺少产品信息，放弃开仓", inst_id);
+                        return None;
+                    };
+                    let slippage = self.plan_entry(&inst_id)?;
+                    let packet = self.order_packet(&inst, Side::Buy, OrderSize::Quote { amount: slippage.notional, ref_px: slippage.est_px }, "en")?;
+                    self.state.pending_entries.write().unwrap().insert(inst_id.clone(), PendingEntry {
+                        cl_ord_id: packet.cl_ord_id().to_string(),
+                        since_ts: now,
+                        slippage,
+                    });
+                    return Some(packet);
                 }
             }
         }
         None
     }
 
+    /// 🌊 [抛售放量] 只有价格下跌、没有主动卖盘放量的"阴跌"不接
+    /// 要求窗口内主动卖出金额占比和绝对金额都达到阈值
+    fn is_capitulation(&self, inst_id: &str, now: i64) -> bool {
+        let Some(tape) = self.tape.stats(inst_id, CAPITULATION_WINDOW_MS, now) else {
+            warn!("⚠️ [成交流] {} 暂无成交数据，放弃开仓", inst_id);
+            return false;
+        };
+        let largest: Vec<String> = tape.largest.iter()
+            .map(|p| format!("{} {:.0}U@{}", p.side.as_str(), p.notional(), p.px))
+            .collect();
+        info!(
+            "🌊 [成交流] {} {}s 内 {} 笔 ({:.1} 笔/s) | 主动买 {:.0} U | 主动卖 {:.0} U ({:.0}%) | VWAP {} | 大单 [{}]",
+            inst_id, CAPITULATION_WINDOW_MS / 1000, tape.trades, tape.trade_rate(), tape.buy_notional,
+            tape.sell_notional, tape.sell_ratio() * 100.0, tape.vwap, largest.join(", ")
+        );
+        if tape.sell_ratio() < CAPITULATION_SELL_RATIO || tape.sell_notional < CAPITULATION_MIN_SELL_USDT {
+            info!("⏭️ [成交流] {} 未见抛售放量，跳过", inst_id);
+            return false;
+        }
+        true
+    }
+
+    /// 📐 [滑点预估] 按本地深度模拟吃单：超出预算则缩到预算内能吃下的金额，缩得太小就放弃
+    /// 暴跌时山寨币盘口很薄，按 Ask1 估算会严重低估真实成本
+    fn plan_entry(&self, inst_id: &str) -> Option<EntrySlippage> {
+        let plan = self.books.with_book(inst_id, |book| {
+            let full = book.sweep(Side::Buy, BET_SIZE_USDT)?;
+            if full.complete && full.slippage() <= MAX_ENTRY_SLIPPAGE {
+                return Some((full, BET_SIZE_USDT));
+            }
+            // 金额保留两位小数 (向下取整)，避免浮点尾数
+            let notional = (book.max_notional(Side::Buy, MAX_ENTRY_SLIPPAGE).min(BET_SIZE_USDT) * 100.0).floor() / 100.0;
+            Some((full, notional))
+        });
+        let Some(Some((full, notional))) = plan else {
+            warn!("⚠️ [滑点] {} 本地深度未同步，放弃开仓", inst_id);
+            return None;
+        };
+
+        if notional < MIN_BET_SIZE_USDT {
+            warn!(
+                "🛑 [滑点] {} 吃 {} U 预估均价 {} (Ask1 {}) 滑点 {:.2}% 超出预算 {:.2}%，预算内仅 {:.2} U，放弃开仓",
+                inst_id, BET_SIZE_USDT, full.avg_px, full.best_px, full.slippage() * 100.0, MAX_ENTRY_SLIPPAGE * 100.0, notional
+            );
+            return None;
+        }
+        let est = if notional < BET_SIZE_USDT {
+            let est = self.books.with_book(inst_id, |book| book.sweep(Side::Buy, notional)).flatten()?;
+            warn!(
+                "✂️ [滑点] {} 吃 {} U 预估滑点 {:.2}% 超出预算，缩单至 {:.2} U (预估滑点 {:.2}%)",
+                inst_id, BET_SIZE_USDT, full.slippage() * 100.0, notional, est.slippage() * 100.0
+            );
+            est
+        } else {
+            full
+        };
+        info!("📐 [滑点] {} 预估均价 {} | Ask1 {} | 最差价 {} | 预估滑点 {:.3}%", inst_id, est.avg_px, est.best_px, est.worst_px, est.slippage() * 100.0);
+        Some(EntrySlippage { ref_px: est.best_px, est_px: est.avg_px, notional })
+    }
+
+    /// 构造市价单并登记到订单管理器
+    fn order_packet(&self, inst: &Instrument, side: Side, size: OrderSize, signal: &str) -> Option<OpPacket> {
+        match OrderRequest::market(inst, side, size).origin(STRATEGY_CODE, signal).build() {
+            Ok(packet) => {
+                self.orders.lock().unwrap().track(&packet);
+                Some(packet)
+            }
+            Err(e) => { error!("❌ [{}] 订单构造失败: {}", inst.inst_id, e); None }
+        }
+    }
+
+    /// 为超时订单构造撤单请求
+    fn cancel_packets(&self, updates: &[OrderUpdate]) -> Vec<OpPacket> {
+        let mut packets = Vec::new();
+        for update in updates.iter().filter(|u| u.state == OrderState::TimedOut) {
+            let req = CancelRequest { inst_id: &update.inst_id, cl_ord_id: &update.cl_ord_id };
+            match protocol::create_cancel_packet(req) {
+                Ok(packet) => packets.push(packet),
+                Err(e) => error!("❌ [{}] 撤单构造失败: {}", update.inst_id, e),
+            }
+        }
+        packets
+    }
+
+    fn update_orders(&self, data: Option<&serde_json::value::RawValue>) {
+        let Some(raw) = data else { return };
+        let pushes: Vec<Order> = match serde_json::from_str(raw.get()) {
+            Ok(p) => p,
+            Err(e) => { error!("❌ [订单] 推送解析失败: {}", e); return; }
+        };
+        let updates: Vec<OrderUpdate> = {
+            let mut orders = self.orders.lock().unwrap();
+            pushes.into_iter().filter_map(|p| orders.on_push(p)).collect()
+        };
+        self.on_order_updates(updates);
+    }
+
+    /// 📣 订单状态变化 (成交 / 拒绝 / 超时)
+    /// orders 频道按 ANY 订阅，会收到账户下所有订单；只处理本策略本次运行发出的订单
+    fn on_order_updates(&self, updates: Vec<OrderUpdate>) {
+        for update in updates {
+            if !order_id::is_own(&update.cl_ord_id, STRATEGY_CODE) {
+                info!("👀 [订单] {} {} {:?} 非本策略订单 ({})，忽略", update.inst_id, update.side.as_str(), update.state, update.cl_ord_id);
+                continue;
+            }
+            if let Some(fill) = &update.fill {
+                info!(
+                    "🧾 [成交] {} {} {} @ {} | 手续费 {} {} | {:?} | {} #{}",
+                    update.inst_id, update.side.as_str(), fill.sz, fill.px, fill.fee, fill.fee_ccy, update.state, update.cl_ord_id, fill.trade_id
+                );
+                match update.side {
+                    Side::Buy => self.on_entry_fill(&update.inst_id, fill),
+                    Side::Sell => self.on_exit_fill(&update.inst_id, fill),
+                }
+            }
+            if update.state == OrderState::TimedOut {
+                warn!("⏳ [订单] {} {} 未确认，已补发撤单，等待撤单结果或终态推送", update.inst_id, update.cl_ord_id);
+            }
+            match update.side {
+                Side::Buy => self.settle_entry_intent(&update),
+                Side::Sell => self.settle_exit(&update),
+            }
+        }
+    }
+
+    /// 买入成交 -> 建立 / 累加持仓 (按成交量加权均价)
+    fn on_entry_fill(&self, inst_id: &str, fill: &Fill) {
+        let mut pos_map = self.state.positions.write().unwrap();
+        let pos = pos_map.entry(inst_id.to_string()).or_insert_with(|| Position {
+            inst_id: inst_id.to_string(),
+            entry_price: 0.0,
+            size: 0.0,
+            fees: HashMap::new(),
+            entry_ts: time::get_timestamp_ms(),
+            sold: 0.0,
+            exit: None,
+            slippage: None,
+        });
+        if pos.slippage.is_none() {
+            pos.slippage = self.state.pending_entries.read().unwrap().get(inst_id).map(|p| p.slippage);
+        }
+        pos.add_fill(fill);
+        info!("📦 [持仓] {} 数量 {} | 均价 {} | 手续费 {:?}", pos.inst_id, pos.size, pos.entry_price, pos.fees);
+    }
+
+    /// 卖出成交 -> 累计已卖数量 (计价币手续费一并记录)
+    fn on_exit_fill(&self, inst_id: &str, fill: &Fill) {
+        let mut pos_map = self.state.positions.write().unwrap();
+        if let Some(pos) = pos_map.get_mut(inst_id) {
+            pos.sold += fill.sz;
+            *pos.fees.entry(fill.fee_ccy.clone()).or_insert(0.0) += fill.fee;
+        }
+    }
+
+    /// 平仓单终结：全部成交且无剩余才移除持仓；否则安排重试，连续失败则升级告警
+    /// 超时不算终结：旧卖单可能仍挂在交易所，等撤单结果 / 终态推送后再发新卖单，避免重复卖出
+    fn settle_exit(&self, update: &OrderUpdate) {
+        if !update.state.is_terminal() {
+            return;
+        }
+        let mut pos_map = self.state.positions.write().unwrap();
+        let Some(pos) = pos_map.get_mut(&update.inst_id) else { return };
+        let remaining = pos.sellable_qty();
+        let Some(exit) = pos.exit.as_mut() else { return };
+        if exit.cl_ord_id.as_deref() != Some(update.cl_ord_id.as_str()) {
+            return;
+        }
+        exit.cl_ord_id = None;
+
+        if update.state == OrderState::Filled {
+            let is_dust = self.instruments.get(&update.inst_id)
+                .is_none_or(|inst| inst.check_size(remaining).is_err());
+            if is_dust {
+                let slippage = pos.slippage
+                    .map(|s| format!("预估 {:.3}% / 实际 {:.3}%", s.estimated() * 100.0, s.actual(pos.entry_price) * 100.0))
+                    .unwrap_or_else(|| "未知".to_string());
+                info!("✅ [平仓] {} ({}) 完成 | 卖出 {} | 手续费 {:?} | 开仓滑点 {}", update.inst_id, exit.reason, pos.sold, pos.fees, slippage);
+                pos_map.remove(&update.inst_id);
+            } else {
+                warn!("🔁 [平仓] {} 仍有剩余 {}，继续卖出", update.inst_id, remaining);
+            }
+            return;
+        }
+
+        let now = time::get_timestamp_ms();
+        if exit.attempts >= EXIT_ESCALATE_AFTER {
+            error!(
+                "🚨 [平仓失败] {} 已连续失败 {} 次 ({:?})，剩余 {} 未卖出，请人工介入！{}s 后继续重试",
+                update.inst_id, exit.attempts, update.state, remaining, EXIT_ESCALATED_DELAY_MS / 1000
+            );
+            exit.retry_at = now + EXIT_ESCALATED_DELAY_MS;
+        } else {
+            warn!("⚠️ [平仓] {} 卖单未成交 ({:?})，{}ms 后重试", update.inst_id, update.state, EXIT_RETRY_DELAY_MS);
+            exit.retry_at = now + EXIT_RETRY_DELAY_MS;
+        }
+    }
+
+    /// 开仓订单终结 (全部成交 / 撤销 / 拒绝) 后释放开仓意图
+    /// 超时的订单仍可能在交易所成交，意图保留到撤单有结果 (撤销推送 / 查无此单) 为止，避免重复开仓
+    fn settle_entry_intent(&self, update: &OrderUpdate) {
+        if !update.state.is_terminal() {
+            return;
+        }
+        // 与 analyze_ticker 保持相同的加锁顺序: 先 positions 后 pending_entries
+        let entry_price = self.state.positions.read().unwrap().get(&update.inst_id).map(|p| p.entry_price);
+        let mut pending = self.state.pending_entries.write().unwrap();
+        if pending.get(&update.inst_id).is_some_and(|p| p.cl_ord_id == update.cl_ord_id) {
+            if let Some(entry) = pending.remove(&update.inst_id) {
+                info!("🔓 [开仓] {} 意图结束: {:?} (在途 {}ms)", update.inst_id, update.state, time::get_timestamp_ms() - entry.since_ts);
+                // 预估 vs 实际滑点 (实际以持仓成交均价计)
+                if let Some(px) = entry_price.filter(|px| *px > 0.0) {
+                    let slip = entry.slippage;
+                    info!(
+                        "📐 [滑点] {} {} U | Ask1 {} | 预估均价 {} ({:.3}%) | 实际均价 {} ({:.3}%)",
+                        update.inst_id, slip.notional, slip.ref_px, slip.est_px, slip.estimated() * 100.0, px, slip.actual(px) * 100.0
+                    );
+                }
+            }
+        }
+    }
+
     fn update_balance(&self, data: Option<&serde_json::value::RawValue>) {
         if let Some(raw) = data {
             if let Ok(acc) = serde_json::from_str::<Vec<AccountData>>(raw.get()) {
@@ -1,7 +1,7 @@
 use colored::*;
-use chrono::Utc;
 
 use crate::okx::market_data::Ticker;
+use crate::utils::time;
 
 pub struct LogFormatter;
 
@@ -17,7 +17,7 @@ impl LogFormatter {
         // 2. ⏱️ 延迟计算
         // 解析 OKX 时间戳 (如果解析失败默认为 0)
         let remote_ts = ticker.ts.parse::<i64>().unwrap_or(0);
-        let local_ts = Utc::now().timestamp_millis();
+        let local_ts = time::get_timestamp_ms(); // 已按服务器时间校准
         let latency = local_ts - remote_ts;
 
         // 3. 🎨 动态颜色判断
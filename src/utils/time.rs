@@ -1,11 +1,102 @@
 use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
 
-/// 获取当前 UTC 时间戳 (秒级) - 用于 OKX 鉴权
+// ==========================================
+// ⏱️ 服务器时钟同步
+// server_time ≈ local_time + offset
+// ==========================================
+static CLOCK_OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+// 交易所推送给出的偏移量下界 (及其记录时间)，只在 FLOOR_TTL_MS 内有效
+static FLOOR_OFFSET_MS: AtomicI64 = AtomicI64::new(i64::MIN);
+static FLOOR_AT_MS: AtomicI64 = AtomicI64::new(i64::MIN);
+static CLOCK_SAMPLES: Mutex<VecDeque<ClockSample>> = Mutex::new(VecDeque::new());
+
+const MAX_SAMPLES: usize = 32;
+// 只在最近 N 个样本里挑 RTT 最小的那个 (网络抖动越小，估计越准)
+const BEST_OF_RECENT: usize = 8;
+const FLOOR_TTL_MS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClockSample {
+    pub local_ms: i64,
+    pub offset_ms: i64,
+    pub rtt_ms: i64,
+}
+
+/// 获取本机 UTC 时间戳 (毫秒级，未校准)
+pub fn local_timestamp_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// 获取校准后的 UTC 时间戳 (秒级) - 用于 OKX 鉴权
 pub fn get_timestamp_sec() -> String {
-    Utc::now().timestamp().to_string()
+    get_timestamp_ms().div_euclid(1000).to_string()
 }
 
-/// 获取当前 UTC 时间戳 (毫秒级) - 用于计算延迟
+/// 获取校准后的 UTC 时间戳 (毫秒级) - 用于计算延迟 / 行情新鲜度
 pub fn get_timestamp_ms() -> i64 {
-    Utc::now().timestamp_millis()
-}
\ No newline at end of file
+    let local = local_timestamp_ms();
+    local + offset_at(local)
+}
+
+pub fn offset_ms() -> i64 {
+    offset_at(local_timestamp_ms())
+}
+
+/// 生效偏移 = max(REST 估计, 仍在有效期内的推送下界)
+fn offset_at(local_ms: i64) -> i64 {
+    let offset = CLOCK_OFFSET_MS.load(Ordering::Relaxed);
+    if local_ms.saturating_sub(FLOOR_AT_MS.load(Ordering::Relaxed)) <= FLOOR_TTL_MS {
+        offset.max(FLOOR_OFFSET_MS.load(Ordering::Relaxed))
+    } else {
+        offset
+    }
+}
+
+/// 📥 记录一次 REST 校时样本 (NTP 式估计: 假设去程与回程耗时相同)
+/// 返回本次样本的偏移量
+pub fn record_sample(local_send_ms: i64, server_ms: i64, local_recv_ms: i64) -> ClockSample {
+    let rtt_ms = (local_recv_ms - local_send_ms).max(0);
+    let sample = ClockSample {
+        local_ms: local_recv_ms,
+        offset_ms: server_ms - (local_send_ms + rtt_ms / 2),
+        rtt_ms,
+    };
+
+    let mut samples = CLOCK_SAMPLES.lock().unwrap();
+    samples.push_back(sample);
+    while samples.len() > MAX_SAMPLES {
+        samples.pop_front();
+    }
+
+    if let Some(best) = samples.iter().rev().take(BEST_OF_RECENT).min_by_key(|s| s.rtt_ms) {
+        CLOCK_OFFSET_MS.store(best.offset_ms, Ordering::Relaxed);
+    }
+    sample
+}
+
+/// 📡 用交易所推送的时间戳做下界修正
+/// 交易所事件不可能发生在"未来"，推送时间戳晚于本机时间说明偏移量至少为 server_ms - local
+/// 下界只在最近 FLOOR_TTL_MS 内有效，过期后回落到 REST 估计，不会把偏移量永久抬高
+pub fn observe_exchange_ts(server_ms: i64) {
+    let local = local_timestamp_ms();
+    let lower = server_ms - local;
+    let expired = local.saturating_sub(FLOOR_AT_MS.load(Ordering::Relaxed)) > FLOOR_TTL_MS;
+    if expired || lower > FLOOR_OFFSET_MS.load(Ordering::Relaxed) {
+        FLOOR_OFFSET_MS.store(lower, Ordering::Relaxed);
+        FLOOR_AT_MS.store(local, Ordering::Relaxed);
+    }
+}
+
+/// 📈 本机时钟相对服务器的漂移速率 (ppm)，样本不足时返回 None
+pub fn drift_ppm() -> Option<f64> {
+    let samples = CLOCK_SAMPLES.lock().unwrap();
+    let (first, last) = (samples.front()?, samples.back()?);
+    let elapsed_ms = last.local_ms - first.local_ms;
+    if elapsed_ms < 60_000 {
+        return None;
+    }
+    Some((last.offset_ms - first.offset_ms) as f64 / elapsed_ms as f64 * 1_000_000.0)
+}